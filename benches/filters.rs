@@ -0,0 +1,118 @@
+//! Compares the word-wise filter operations against the byte-wise loops they replaced.
+//!
+//! Run with `cargo bench --bench filters`.
+
+use kamilata::prelude::*;
+use rand::Rng;
+use std::{hint::black_box, time::{Duration, Instant}};
+
+const N: usize = 125000;
+const ITERATIONS: u32 = 200;
+
+fn bench(name: &str, mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    println!("{name:<32} {elapsed:>12?}");
+    elapsed
+}
+
+fn compare(name: &str, bytewise: impl FnMut(), wordwise: impl FnMut()) {
+    let bytewise = bench(&format!("{name} (bytes)"), bytewise);
+    let wordwise = bench(&format!("{name} (words)"), wordwise);
+    println!("{:<32} {:>11.1}x\n", "speedup", bytewise.as_secs_f64() / wordwise.as_secs_f64());
+}
+
+fn random_filter(rng: &mut impl Rng, load: f64) -> Filter<N> {
+    let mut filter = Filter::new();
+    for _ in 0..((N * 8) as f64 * load) as usize {
+        filter.set_bit(rng.gen_range(0..N * 8), true);
+    }
+    filter
+}
+
+fn main() {
+    let mut rng = rand::thread_rng();
+    let filter1 = random_filter(&mut rng, 0.1);
+    let filter2 = random_filter(&mut rng, 0.1);
+    let bytes1: Vec<u8> = (&filter1).into();
+    let bytes2: Vec<u8> = (&filter2).into();
+
+    let mut bytes = bytes1.clone();
+    let mut filter = filter1.clone();
+    compare("or", || {
+        for (a, b) in bytes.iter_mut().zip(bytes2.iter()) {
+            *a |= *b;
+        }
+        black_box(&bytes);
+    }, || {
+        filter.bitor_assign_ref(&filter2);
+        black_box(&filter);
+    });
+
+    let mut bytes = bytes1.clone();
+    let mut filter = filter1.clone();
+    compare("and", || {
+        for (a, b) in bytes.iter_mut().zip(bytes2.iter()) {
+            *a &= *b;
+        }
+        black_box(&bytes);
+    }, || {
+        filter.bitand_assign_ref(&filter2);
+        black_box(&filter);
+    });
+
+    let mut bytes = bytes1.clone();
+    let mut filter = filter1.clone();
+    compare("xor", || {
+        for (a, b) in bytes.iter_mut().zip(bytes2.iter()) {
+            *a ^= *b;
+        }
+        black_box(&bytes);
+    }, || {
+        filter.bitxor_assign_ref(&filter2);
+        black_box(&filter);
+    });
+
+    let mut bytes = bytes1.clone();
+    let mut filter = filter1.clone();
+    compare("and_not", || {
+        for (a, b) in bytes.iter_mut().zip(bytes2.iter()) {
+            *a &= !*b;
+        }
+        black_box(&bytes);
+    }, || {
+        filter.and_not_assign_ref(&filter2);
+        black_box(&filter);
+    });
+
+    compare("count_set_bits", || {
+        black_box(black_box(&bytes1).iter().map(|byte| byte.count_ones() as usize).sum::<usize>());
+    }, || {
+        black_box(black_box(&filter1).count_set_bits());
+    });
+
+    compare("count_common_bits", || {
+        black_box(black_box(&bytes1).iter().zip(bytes2.iter()).map(|(a, b)| (a & b).count_ones() as usize).sum::<usize>());
+    }, || {
+        black_box(black_box(&filter1).count_common_bits(&filter2));
+    });
+
+    let empty = Filter::<N>::new();
+    let empty_bytes = vec![0u8; N];
+    compare("is_empty", || {
+        black_box(black_box(&empty_bytes).iter().all(|byte| *byte == 0));
+    }, || {
+        black_box(black_box(&empty).is_empty());
+    });
+
+    let indices = filter1.iter_set_bits().step_by(97).collect::<Vec<_>>();
+    compare("contains_all", || {
+        black_box(indices.iter().all(|idx| bytes1[idx / 8] & (1 << (idx % 8)) != 0));
+    }, || {
+        black_box(filter1.contains_all(black_box(&indices)));
+    });
+}
@@ -15,6 +15,18 @@ pub enum KamilataEvent {
     /// Sent when a leeching task is aborted.
     /// This can happen even if SeederAdded was not sent.
     SeederRemoved { peer_id: PeerId },
+    /// Sent when a peer gracefully disconnects us.
+    /// We won't dial nor leech from this peer before `try_again_in` has elapsed, or ever if it is None.
+    PeerDisconnected { peer_id: PeerId, reason: String, try_again_in: Option<Duration> },
+    /// Sent when a seeder sends us invalid filters.
+    /// We stop leeching from it and won't leech from it again for an hour.
+    SeederMisbehaved { peer_id: PeerId, error: FilterUpdateError },
+    /// Sent when a peer refuses to seed to us, followed by SeederRemoved.
+    /// We won't leech from this peer before `retry_in` has elapsed, or ever if it is None, but we keep routing searches through it.
+    LeechRejected { peer_id: PeerId, reason: RejectionReason, retry_in: Option<Duration> },
+    /// Sent when a peer can't understand us, as revealed by the handshake made on connection.
+    /// We close the connection and won't dial it again for an hour.
+    IncompatiblePeer { peer_id: PeerId, reason: Incompatibility },
 }
 
 /// Implementation of the Kamilata protocol.
@@ -58,26 +70,7 @@ impl<const N: usize, S: Store<N> + Default> KamilataBehaviour<N, S> {
     }
 
     pub fn new_with_config(our_peer_id: PeerId, config: KamilataConfig) -> KamilataBehaviour<N, S> {
-        let rt_handle = tokio::runtime::Handle::current();
-        let (control_msg_sender, control_msg_receiver) = channel(100);
-        let db_behaviour_controller = BehaviourController {
-            sender: control_msg_sender.clone(),
-        };
-        let config = Arc::new(config);
-
-        KamilataBehaviour {
-            our_peer_id,
-            connections: HashMap::new(),
-            db: Arc::new(Db::new(Arc::clone(&config), S::default(), db_behaviour_controller)),
-            config,
-            control_msg_sender,
-            control_msg_receiver,
-            pending_handler_events: BTreeMap::new(),
-            handler_event_queue: Vec::new(),
-            rt_handle,
-            task_counter: Counter::new(0),
-            tasks: HashMap::new(),
-        }
+        Self::new_with_config_and_store(our_peer_id, config, S::default())
     }
 }
 
@@ -94,18 +87,26 @@ impl<const N: usize, S: Store<N>> KamilataBehaviour<N, S> {
         };
         let config = Arc::new(config);
 
+        let db = Arc::new(Db::new(Arc::clone(&config), store, db_behaviour_controller));
+
+        let task_counter = Counter::new(0);
+        let mut tasks: HashMap<usize, Task> = HashMap::new();
+        if config.seeder_rotation.is_some() {
+            tasks.insert(task_counter.next() as usize, Box::pin(rotate_seeders(Arc::clone(&db), our_peer_id)));
+        }
+
         KamilataBehaviour {
             our_peer_id,
             connections: HashMap::new(),
-            db: Arc::new(Db::new(Arc::clone(&config), store, db_behaviour_controller)),
+            db,
             config,
             control_msg_sender,
             control_msg_receiver,
             pending_handler_events: BTreeMap::new(),
             handler_event_queue: Vec::new(),
             rt_handle,
-            task_counter: Counter::new(0),
-            tasks: HashMap::new(),
+            task_counter,
+            tasks,
         }
     }
 
@@ -145,6 +146,32 @@ impl<const N: usize, S: Store<N>> KamilataBehaviour<N, S> {
         self.handler_event_queue.push((seeder, BehaviorToHandlerEvent::StopSeeding));
     }
 
+    /// Tells the behaviour that documents of the store have changed.
+    /// Our leechers then receive the new filters as soon as the negotiated minimum interval allows it, instead of waiting for the next periodic update.
+    pub fn notify_store_changed(&self) {
+        self.db.notify_store_changed();
+    }
+
+    /// Blocks a peer so that its documents are kept out of our view of the network.
+    /// We stop leeching from it, ignore routes leading to it, and ask our seeders to leave it out of the filters they send us.
+    pub fn block_peer(&mut self, peer_id: PeerId) {
+        if self.db.block_peer(peer_id) {
+            self.stop_leeching(peer_id);
+        }
+    }
+
+    /// Unblocks a peer previously blocked with [KamilataBehaviour::block_peer].
+    pub fn unblock_peer(&mut self, peer_id: &PeerId) {
+        self.db.unblock_peer(peer_id);
+    }
+
+    /// Gracefully disconnects a peer, explaining why and when it may come back.
+    /// Passing None as `try_again_in` asks the peer to never come back.
+    pub fn disconnect(&mut self, peer_id: PeerId, reason: impl Into<String>, try_again_in: Option<Duration>) {
+        let packet = DisconnectPacket::new(reason, try_again_in);
+        self.handler_event_queue.push((peer_id, BehaviorToHandlerEvent::Disconnect(packet)));
+    }
+
     /// Starts a new search and returns an [handler](OngoingSearchControler) to control it.
     pub async fn search(&mut self, query: impl Into<S::Query>) -> OngoingSearchController<N, S> {
         self.search_with_config(query, SearchConfig::default()).await
@@ -201,6 +228,9 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
                 tokio::spawn(async move {
                     db2.add_peer(peer_id, addrs).await;
                 });
+                if self.config.auto_leech.is_some() && info.other_established == 0 {
+                    self.tasks.insert(self.task_counter.next() as usize, Box::pin(auto_leech_from(Arc::clone(&self.db), self.our_peer_id, peer_id)));
+                }
             },
             FromSwarm::DialFailure(info) => {
                 if let Some(peer_id) = info.peer_id {
@@ -215,8 +245,11 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
                     self.handler_event_queue.retain(|(peer_id, _)| peer_id != &info.peer_id);
                     self.pending_handler_events.remove(&info.peer_id);
                     let db2 = Arc::clone(&self.db);
+                    let our_peer_id = self.our_peer_id;
                     tokio::spawn(async move {
                         db2.remove_peer(&info.peer_id).await;
+                        // The peer might have been a seeder
+                        fill_seeder_slots(db2, our_peer_id).await;
                     });
                 }
             },
@@ -242,7 +275,24 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
         _local_addr: &Multiaddr,
         _remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(KamilataHandler::new(self.our_peer_id, remote_peer_id, Arc::clone(&self.db), Arc::clone(&self.config)))
+        Ok(KamilataHandler::new(self.our_peer_id, remote_peer_id, Endpoint::Listener, Arc::clone(&self.db), Arc::clone(&self.config)))
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        // Respects the wish of peers that disconnected us
+        if let Some(peer_id) = maybe_peer {
+            if self.db.is_banned(&peer_id) {
+                debug!("{} Not dialing {peer_id} as it asked us not to come back yet", self.our_peer_id);
+                return Err(ConnectionDenied::new(BannedPeer));
+            }
+        }
+        Ok(Vec::new())
     }
 
     fn handle_established_outbound_connection(
@@ -252,7 +302,12 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
         _addr: &Multiaddr,
         _role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(KamilataHandler::new(self.our_peer_id, peer, Arc::clone(&self.db), Arc::clone(&self.config)))
+        // The peer might have been dialed by address only
+        if self.db.is_banned(&peer) {
+            debug!("{} Closing the connection to {peer} as it asked us not to come back yet", self.our_peer_id);
+            return Err(ConnectionDenied::new(BannedPeer));
+        }
+        Ok(KamilataHandler::new(self.our_peer_id, peer, Endpoint::Dialer, Arc::clone(&self.db), Arc::clone(&self.config)))
     }
 
     fn poll(
@@ -273,10 +328,22 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
         if let Poll::Ready(Some(control_message)) = self.control_msg_receiver.poll_recv(cx) {
             match control_message {
                 BehaviourControlMessage::OutputEvent(event) => {
+                    if let (KamilataEvent::SeederRemoved { .. }, Some(_)) = (&event, &self.config.auto_leech) {
+                        self.tasks.insert(self.task_counter.next() as usize, Box::pin(fill_seeder_slots(Arc::clone(&self.db), self.our_peer_id)));
+                        cx.waker().wake_by_ref();
+                    }
                     return Poll::Ready(
                         ToSwarm::GenerateEvent(event)
                     );
                 }
+                BehaviourControlMessage::CloseConnection(peer_id) => {
+                    return Poll::Ready(
+                        ToSwarm::CloseConnection {
+                            peer_id,
+                            connection: CloseConnection::All,
+                        }
+                    );
+                }
                 BehaviourControlMessage::DialPeerAndMessage(peer_id, addresses, event) => {
                     // Just notify the handler directly if we are already connected to the peer.
                     trace!("{} Dialing peer {peer_id} with addresses {addresses:?} and sending message", self.our_peer_id);
@@ -289,6 +356,11 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
                             }
                         );
                     }
+                    if self.db.is_banned(&peer_id) {
+                        debug!("{} Not dialing {peer_id} as it asked us not to come back yet, dropping {event:?}", self.our_peer_id);
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
                     self.pending_handler_events.insert(peer_id, event);
                     return Poll::Ready(
                         ToSwarm::Dial {
@@ -328,6 +400,7 @@ impl<const N: usize, S: Store<N>> NetworkBehaviour for KamilataBehaviour<N, S> {
 #[derive(Debug)]
 pub(crate) enum BehaviourControlMessage<const N: usize, S: Store<N>> {
     DialPeerAndMessage(PeerId, Vec<Multiaddr>, BehaviorToHandlerEvent<N, S>),
+    CloseConnection(PeerId),
     OutputEvent(KamilataEvent),
 }
 
@@ -350,6 +423,13 @@ impl<const N: usize, S: Store<N>> BehaviourController<N, S> {
         }
     }
 
+    /// Requests behaviour to close all connections to a peer.
+    pub async fn close_connection(&self, peer_id: PeerId) {
+        if let Err(e) = self.sender.send(BehaviourControlMessage::CloseConnection(peer_id)).await {
+            error!("Failed to close connection {e}");
+        }
+    }
+
     /// Outputs an event from the behaviour.
     pub async fn emit_event(&self, event: KamilataEvent) {
         if let Err(e) = self.sender.send(BehaviourControlMessage::OutputEvent(event)).await {
@@ -0,0 +1,128 @@
+//! What nodes must agree on to understand each other.
+//!
+//! Peers exchange their [Capabilities] as soon as they connect, and close the connection if they are incompatible.
+//! See [KamilataEvent::IncompatiblePeer].
+
+use crate::prelude::*;
+
+/// Version of the Kamilata protocol implemented by this crate.
+/// Peers implementing another version are refused.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Optional features implemented by this crate.
+/// Peers lacking some of them can still talk to us.
+pub const FEATURES: &[&str] = &["filter-deltas", "leech-rejections", "leecher-preemption"];
+
+/// Describes how a node builds and exchanges filters.
+#[derive(protocol::Protocol, Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub version: u32,
+    /// Size of filters in bytes
+    pub filter_size: u32,
+    pub hasher: HasherIdentity,
+    pub tokenizer: TokenizerIdentity,
+    /// Encodings the node can decode filters from
+    pub encodings: Vec<FilterEncoding>,
+    /// Optional features the node supports
+    pub features: Vec<String>,
+}
+
+impl Capabilities {
+    /// Returns the capabilities of a node using filters of `N` bytes and the store `S`.
+    pub fn of<const N: usize, S: Store<N>>() -> Capabilities {
+        Capabilities {
+            version: PROTOCOL_VERSION,
+            filter_size: N as u32,
+            hasher: S::HASHER.identity(),
+            tokenizer: S::TOKENIZER.identity(),
+            encodings: FilterEncoding::all(),
+            features: FEATURES.iter().map(|feature| feature.to_string()).collect(),
+        }
+    }
+
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// Returns an error if a node with these capabilities can't talk to a node with the other ones.
+    pub fn check(&self, theirs: &Capabilities) -> Result<(), Incompatibility> {
+        if self.version != theirs.version {
+            return Err(Incompatibility::Version { ours: self.version, theirs: theirs.version });
+        }
+        if self.filter_size != theirs.filter_size {
+            return Err(Incompatibility::FilterSize { ours: self.filter_size as usize, theirs: theirs.filter_size as usize });
+        }
+        if self.hasher != theirs.hasher {
+            return Err(Incompatibility::Hasher { ours: self.hasher.clone(), theirs: theirs.hasher.clone() });
+        }
+        if self.tokenizer != theirs.tokenizer {
+            return Err(Incompatibility::Tokenizer { ours: self.tokenizer.clone(), theirs: theirs.tokenizer.clone() });
+        }
+        if !self.encodings.iter().any(|encoding| theirs.encodings.contains(encoding)) {
+            return Err(Incompatibility::Encodings { ours: self.encodings.clone(), theirs: theirs.encodings.clone() });
+        }
+        Ok(())
+    }
+}
+
+/// Why we can't talk to a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The peer implements another version of the protocol.
+    Version { ours: u32, theirs: u32 },
+    /// The peer's filters have another size, in bytes.
+    FilterSize { ours: usize, theirs: usize },
+    /// The peer hashes words differently, so its filters would be meaningless to us.
+    Hasher { ours: HasherIdentity, theirs: HasherIdentity },
+    /// The peer splits text into words differently, so our queries wouldn't match its documents.
+    Tokenizer { ours: TokenizerIdentity, theirs: TokenizerIdentity },
+    /// We share no filter encoding.
+    Encodings { ours: Vec<FilterEncoding>, theirs: Vec<FilterEncoding> },
+}
+
+impl std::fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Incompatibility::Version { ours, theirs } => write!(f, "protocol version {theirs} is not supported (ours: {ours})"),
+            Incompatibility::FilterSize { ours, theirs } => write!(f, "filters of {theirs} bytes are not supported (ours: {ours} bytes)"),
+            Incompatibility::Hasher { ours, theirs } => write!(f, "words are hashed differently (ours: {ours:?}, theirs: {theirs:?})"),
+            Incompatibility::Tokenizer { ours, theirs } => write!(f, "text is tokenized differently (ours: {ours:?}, theirs: {theirs:?})"),
+            Incompatibility::Encodings { ours, theirs } => write!(f, "no common filter encoding (ours: {ours:?}, theirs: {theirs:?})"),
+        }
+    }
+}
+
+impl std::error::Error for Incompatibility {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatibility() {
+        let ours = Capabilities {
+            version: PROTOCOL_VERSION,
+            filter_size: 125000,
+            hasher: DoubleHasher::DEFAULT.identity(),
+            tokenizer: UnicodeTokenizer::DEFAULT.identity(),
+            encodings: FilterEncoding::all(),
+            features: FEATURES.iter().map(|feature| feature.to_string()).collect(),
+        };
+        assert!(ours.supports("filter-deltas"));
+        assert_eq!(ours.check(&ours), Ok(()));
+
+        let mut theirs = Capabilities { features: Vec::new(), encodings: vec![FilterEncoding::Bitmap], ..ours.clone() };
+        assert_eq!(ours.check(&theirs), Ok(()));
+        theirs.encodings.clear();
+        assert!(matches!(ours.check(&theirs), Err(Incompatibility::Encodings { .. })));
+
+        let theirs = Capabilities { filter_size: 1000, ..ours.clone() };
+        assert_eq!(ours.check(&theirs), Err(Incompatibility::FilterSize { ours: 125000, theirs: 1000 }));
+        let theirs = Capabilities { version: PROTOCOL_VERSION + 1, ..ours.clone() };
+        assert!(matches!(ours.check(&theirs), Err(Incompatibility::Version { .. })));
+        let theirs = Capabilities { hasher: DoubleHasher::new(3, 42).identity(), ..ours.clone() };
+        assert!(matches!(ours.check(&theirs), Err(Incompatibility::Hasher { .. })));
+        let theirs = Capabilities { tokenizer: UnicodeTokenizer::new().with_min_len(3).identity(), ..ours.clone() };
+        assert!(matches!(ours.check(&theirs), Err(Incompatibility::Tokenizer { .. })));
+    }
+}
@@ -100,6 +100,11 @@ pub struct KamilataConfig {
     pub max_seeders: usize,
     /// Maximum number of peers we send filters to (default: 50)
     pub max_leechers: usize,
+    /// Maximum proportion of bits set to 1 in the level-0 filter received from seeders (default: 0.5)
+    /// 
+    /// Seeders whose own filter is more loaded are disconnected, as such filters match almost any query.
+    /// Filters at higher levels aggregate many peers, so they aren't checked.
+    pub max_filter_load: f64,
     /// This closure is called when a peer wants to leech from us.
     /// If it returns true, the peer is allowed to leech.
     /// If this closure is not set, all peers are allowed to leech.
@@ -120,6 +125,26 @@ pub struct KamilataConfig {
     /// # let t: ApprocheLeecherClosure = Box::new(approve_leecher);
     /// ```
     pub approve_leecher: Option<ApprocheLeecherClosure>,
+    /// Gives a priority to each peer approved to leech from us (default: None)
+    /// 
+    /// When `max_leechers` is reached, a peer with a higher priority than our lowest priority leecher takes its spot.
+    /// The preempted leecher is told when it may come back.
+    /// This allows reserving capacity for peers that matter more, such as our own infrastructure nodes.
+    /// 
+    /// When not set, all leechers have the same priority and spots are first-come-first-served.
+    pub leecher_policy: Option<Box<dyn LeecherPolicy>>,
+    /// Lets the behaviour choose which peers we leech from (default: None)
+    /// 
+    /// When set, we start leeching from peers as they connect, until `max_seeders` is reached.
+    /// When a seeder is removed or disconnects, the freed slot is filled with a connected peer chosen by this policy.
+    /// Seeders that were removed are not chosen again for a minute.
+    /// 
+    /// When not set, [KamilataBehaviour::leech_from] must be called manually.
+    pub auto_leech: Option<Box<dyn SeederSelectionPolicy>>,
+    /// Periodically evicts the least useful seeder when a better candidate is connected (default: None)
+    /// 
+    /// The replacement is chosen by `auto_leech`, so this does nothing when `auto_leech` is not set.
+    pub seeder_rotation: Option<SeederRotation>,
 }
 
 impl std::fmt::Debug for KamilataConfig {
@@ -130,10 +155,20 @@ impl std::fmt::Debug for KamilataConfig {
             .field("filter_count", &self.filter_count)
             .field("max_seeders", &self.max_seeders)
             .field("max_leechers", &self.max_leechers)
+            .field("max_filter_load", &self.max_filter_load)
             .field("is_approved_leecher", match self.approve_leecher.is_some() {
                 true => &"Some([closure])",
                 false => &"None",
             })
+            .field("leecher_policy", match self.leecher_policy.is_some() {
+                true => &"Some([policy])",
+                false => &"None",
+            })
+            .field("auto_leech", match self.auto_leech.is_some() {
+                true => &"Some([policy])",
+                false => &"None",
+            })
+            .field("seeder_rotation", &self.seeder_rotation)
             .finish()
     }
 }
@@ -141,12 +176,16 @@ impl std::fmt::Debug for KamilataConfig {
 impl Default for KamilataConfig {
     fn default() -> Self {
         Self {
-            protocol_names: vec![String::from("/kamilata/1.0.0")],
+            protocol_names: vec![String::from("/kamilata/2.0.0")],
             get_filters_interval: MinTargetMax { min: 15_000, target: 20_000, max: 60_000*3 },
             filter_count: 8,
             max_seeders: 20,
             max_leechers: 50,
+            max_filter_load: 0.5,
             approve_leecher: None,
+            leecher_policy: None,
+            auto_leech: None,
+            seeder_rotation: None,
         }
     }
 }
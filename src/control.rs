@@ -62,6 +62,16 @@ pub struct SearchConfig {
     pub req_limit: usize,
     /// Number of milliseconds to wait for a response before considering the peer unresponsive
     pub timeout_ms: usize,
+    /// Whether results with the same [cid](SearchResult::cid) should only be delivered once.
+    /// Additional providers of an already delivered result are then reported as [SearchUpdate::ProviderAdded].
+    pub deduplicate: bool,
+    /// Maximum duration of the whole search, after which ongoing requests are cancelled
+    pub deadline: Option<Duration>,
+    /// Number of results after which the search stops.
+    /// Results received after that are discarded.
+    pub max_results: Option<usize>,
+    /// Maximum number of peers to query
+    pub max_peers: Option<usize>,
 }
 
 impl SearchConfig {
@@ -70,6 +80,10 @@ impl SearchConfig {
             priority,
             req_limit,
             timeout_ms,
+            deduplicate: false,
+            deadline: None,
+            max_results: None,
+            max_peers: None,
         }
     }
 
@@ -94,6 +108,34 @@ impl SearchConfig {
             ..self
         }
     }
+
+    pub fn with_deduplication(self, deduplicate: bool) -> SearchConfig {
+        SearchConfig {
+            deduplicate,
+            ..self
+        }
+    }
+
+    pub fn with_deadline(self, deadline: Duration) -> SearchConfig {
+        SearchConfig {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    pub fn with_max_results(self, max_results: usize) -> SearchConfig {
+        SearchConfig {
+            max_results: Some(max_results),
+            ..self
+        }
+    }
+
+    pub fn with_max_peers(self, max_peers: usize) -> SearchConfig {
+        SearchConfig {
+            max_peers: Some(max_peers),
+            ..self
+        }
+    }
 }
 
 impl Default for SearchConfig {
@@ -106,16 +148,68 @@ impl Default for SearchConfig {
             },
             req_limit: 10,
             timeout_ms: 50000,
+            deduplicate: false,
+            deadline: None,
+            max_results: None,
+            max_peers: None,
         }
     }
 }
 
+/// Why a search ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchFinishReason {
+    /// All known providers have been queried
+    ProvidersExhausted,
+    /// The search was stopped before its end
+    Cancelled,
+    /// The search took longer than allowed
+    DeadlineReached,
+    /// Enough results have been received (see [SearchConfig::max_results])
+    ResultLimitReached,
+    /// The maximum number of peers have been queried (see [SearchConfig::max_peers])
+    PeerLimitReached,
+}
+
+/// An update about the results of an ongoing search, received through [OngoingSearchController::recv_update].
+#[derive(Debug)]
+pub enum SearchUpdate<T: SearchResult> {
+    /// A new result provided by a peer
+    Result(T, PeerId),
+    /// Another peer provides a result that has already been delivered.
+    /// Only sent when [SearchConfig::deduplicate] is enabled.
+    ProviderAdded {
+        cid: T::Cid,
+        peer_id: PeerId,
+    },
+}
+
+/// An event about the progress of an ongoing search, received through [OngoingSearchController::recv_event].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchEvent {
+    /// A request has been sent to this peer
+    PeerQueried(PeerId),
+    /// This peer didn't answer in time
+    PeerTimedOut(PeerId),
+    /// A queried peer told us about this number of new peers to query
+    RoutesDiscovered(usize),
+    /// This peer provided a result that was accepted
+    ResultReceived(PeerId),
+    /// The search is over. This is always the last event.
+    Finished {
+        reason: SearchFinishReason,
+    },
+}
+
 pub(crate) struct OngoingSearchState<const N: usize, S: Store<N>> {
     query: Arc<S::Query>,
     config: SearchConfig,
     queried_peers: usize,
     final_peers: usize,
     ongoing_queries: usize,
+    results_received: usize,
+    /// Peers providing each result, only maintained when [SearchConfig::deduplicate] is enabled
+    providers: HashMap<<S::Result as SearchResult>::Cid, Vec<PeerId>>,
 }
 
 impl<const N: usize, S: Store<N>> OngoingSearchState<N, S> {
@@ -126,20 +220,30 @@ impl<const N: usize, S: Store<N>> OngoingSearchState<N, S> {
             queried_peers: 0,
             final_peers: 0,
             ongoing_queries: 0,
+            results_received: 0,
+            providers: HashMap::new(),
         }
     }
 
     pub(crate) fn into_pair(self) -> (OngoingSearchController<N, S>, OngoingSearchFollower<N, S>) {
         let (sender, receiver) = channel(100);
+        let (event_sender, event_receiver) = unbounded_channel();
+        let (cancel_sender, cancel_receiver) = watch::channel(false);
         let inner = Arc::new(RwLock::new(self));
 
         (
             OngoingSearchController {
                 receiver,
+                finished: false,
+                event_receiver,
+                events_finished: false,
+                cancel_sender,
                 inner: inner.clone(),
             },
             OngoingSearchFollower {
                 sender,
+                event_sender,
+                cancel_receiver,
                 inner,
             },
         )
@@ -148,25 +252,96 @@ impl<const N: usize, S: Store<N>> OngoingSearchState<N, S> {
 
 /// A search controller is an handle to an ongoing search, started with [KamilataBehaviour::search].
 /// It allows getting results asynchronously, and to control the search.
+/// 
+/// It implements [Stream](futures::Stream), yielding results until the search is over.
 pub struct OngoingSearchController<const N: usize, S: Store<N>> {
-    receiver: Receiver<(S::Result, PeerId)>,
+    /// Results of the search, followed by `None` once the search is over
+    receiver: Receiver<Option<SearchUpdate<S::Result>>>,
+    finished: bool,
+    /// Progress of the search, unbounded so that events not being consumed never hold results back
+    event_receiver: UnboundedReceiver<SearchEvent>,
+    events_finished: bool,
+    cancel_sender: watch::Sender<bool>,
     inner: Arc<RwLock<OngoingSearchState<N, S>>>,
 }
 
 pub struct OngoingSearchFollower<const N: usize, S: Store<N>> {
-    sender: Sender<(S::Result, PeerId)>,
+    sender: Sender<Option<SearchUpdate<S::Result>>>,
+    event_sender: UnboundedSender<SearchEvent>,
+    cancel_receiver: WatchReceiver<bool>,
     inner: Arc<RwLock<OngoingSearchState<N, S>>>,
 }
 
 impl<const N: usize, S: Store<N>> OngoingSearchController<N, S> {
+    /// Marks the search as over so that late results are ignored.
+    fn set_finished(&mut self) {
+        self.finished = true;
+        self.receiver.close();
+    }
+
+    fn poll_update(&mut self, cx: &mut Context<'_>) -> Poll<Option<SearchUpdate<S::Result>>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        match self.receiver.poll_recv(cx) {
+            Poll::Ready(Some(Some(update))) => Poll::Ready(Some(update)),
+            Poll::Ready(Some(None)) | Poll::Ready(None) => {
+                self.set_finished();
+                Poll::Ready(None)
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
     /// Waits for the next search result.
+    /// Returns `None` once the search is over.
+    /// [SearchUpdate::ProviderAdded] updates are skipped, but the providers remain available through [OngoingSearchController::providers].
     pub async fn recv(&mut self) -> Option<(S::Result, PeerId)> {
-        self.receiver.recv().await
+        self.next().await
     }
 
     /// Returns the next search result if available.
     pub fn try_recv(&mut self) -> Result<(S::Result, PeerId), tokio::sync::mpsc::error::TryRecvError> {
-        self.receiver.try_recv()
+        use tokio::sync::mpsc::error::TryRecvError;
+        loop {
+            if self.finished {
+                return Err(TryRecvError::Disconnected);
+            }
+            match self.receiver.try_recv() {
+                Ok(Some(SearchUpdate::Result(result, peer_id))) => return Ok((result, peer_id)),
+                Ok(Some(SearchUpdate::ProviderAdded { .. })) => continue,
+                Ok(None) | Err(TryRecvError::Disconnected) => self.set_finished(),
+                Err(TryRecvError::Empty) => return Err(TryRecvError::Empty),
+            }
+        }
+    }
+
+    /// Waits for the next search update, including new providers of already delivered results.
+    /// Returns `None` once the search is over.
+    pub async fn recv_update(&mut self) -> Option<SearchUpdate<S::Result>> {
+        futures::future::poll_fn(|cx| self.poll_update(cx)).await
+    }
+
+    /// Waits for the next search event, which allows following the progress of the search.
+    /// Returns `None` after [SearchEvent::Finished] has been received.
+    /// 
+    /// Events are kept until they are received, so they can be read after the results without slowing the search down.
+    pub async fn recv_event(&mut self) -> Option<SearchEvent> {
+        if self.events_finished {
+            return None;
+        }
+        let event = self.event_receiver.recv().await;
+        if matches!(event, None | Some(SearchEvent::Finished { .. })) {
+            self.events_finished = true;
+            self.event_receiver.close();
+        }
+        event
+    }
+
+    /// Returns the peers known to provide the result with this cid.
+    /// Always empty unless [SearchConfig::deduplicate] is enabled.
+    pub async fn providers(&self, cid: &<S::Result as SearchResult>::Cid) -> Vec<PeerId> {
+        self.inner.read().await.providers.get(cid).cloned().unwrap_or_default()
     }
 
     /// Returns a copy of the ongoing queries.
@@ -219,6 +394,15 @@ impl<const N: usize, S: Store<N>> OngoingSearchController<N, S> {
         self.inner.read().await.ongoing_queries
     }
 
+    /// Cancels the search right away.
+    /// Ongoing requests are aborted and the queried peers are told to stop searching.
+    /// A [SearchEvent::Finished] event with [SearchFinishReason::Cancelled] will follow.
+    /// 
+    /// Dropping the controller also cancels the search.
+    pub fn cancel(&self) {
+        self.cancel_sender.send_replace(true);
+    }
+
     /// Stops the search and returns all search results that have not been consumed yet.
     pub async fn finish(mut self) -> SearchResults<S::Result> {
         let mut search_results = Vec::new();
@@ -226,20 +410,76 @@ impl<const N: usize, S: Store<N>> OngoingSearchController<N, S> {
             search_results.push(search_result);
         }
 
-        let inner = self.inner.read().await;
+        let mut inner = self.inner.write().await;
 
         SearchResults {
             hits: search_results,
+            providers: std::mem::take(&mut inner.providers),
             queried_peers: inner.queried_peers,
             final_peers: inner.final_peers,
         }
     }
 }
 
+impl<const N: usize, S: Store<N>> futures::Stream for OngoingSearchController<N, S> {
+    type Item = (S::Result, PeerId);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.poll_update(cx) {
+                Poll::Ready(Some(SearchUpdate::Result(result, peer_id))) => return Poll::Ready(Some((result, peer_id))),
+                Poll::Ready(Some(SearchUpdate::ProviderAdded { .. })) => continue,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
 impl<const N: usize, S: Store<N>> OngoingSearchFollower<N, S> {
     /// Sends a search result to the controler.
-    pub async fn send(&self, search_result: (S::Result, PeerId)) -> Result<(), tokio::sync::mpsc::error::SendError<(S::Result, PeerId)>> {
-        self.sender.send(search_result).await
+    /// When deduplication is enabled, results that have already been sent are turned into [SearchUpdate::ProviderAdded] updates.
+    pub async fn send(&self, search_result: (S::Result, PeerId)) -> Result<(), SearchInterrupted> {
+        let (result, peer_id) = search_result;
+        let update = {
+            let mut inner = self.inner.write().await;
+            if inner.config.max_results.is_some_and(|max_results| inner.results_received >= max_results) {
+                return Err(SearchInterrupted);
+            }
+            if inner.config.deduplicate {
+                match inner.providers.entry(result.cid()) {
+                    std::collections::hash_map::Entry::Occupied(mut entry) => {
+                        if entry.get().contains(&peer_id) {
+                            return Ok(());
+                        }
+                        entry.get_mut().push(peer_id);
+                        SearchUpdate::ProviderAdded { cid: result.cid(), peer_id }
+                    },
+                    std::collections::hash_map::Entry::Vacant(entry) => {
+                        entry.insert(vec![peer_id]);
+                        inner.results_received += 1;
+                        SearchUpdate::Result(result, peer_id)
+                    },
+                }
+            } else {
+                inner.results_received += 1;
+                SearchUpdate::Result(result, peer_id)
+            }
+        };
+        self.send_event(SearchEvent::ResultReceived(peer_id));
+        self.sender.send(Some(update)).await.map_err(|_| SearchInterrupted)
+    }
+
+    /// Sends a progress event to the controler, without waiting for it to be received.
+    pub fn send_event(&self, event: SearchEvent) {
+        let _ = self.event_sender.send(event);
+    }
+
+    /// Tells the controler the search is over.
+    /// [SearchEvent::Finished] is sent right away, and the stream of results ends after the results already sent.
+    pub async fn finish(&self, reason: SearchFinishReason) {
+        self.send_event(SearchEvent::Finished { reason });
+        let _ = self.sender.send(None).await;
     }
 
     /// Detects if search is closed
@@ -247,6 +487,21 @@ impl<const N: usize, S: Store<N>> OngoingSearchFollower<N, S> {
         self.sender.is_closed()
     }
 
+    /// Detects if the search has been cancelled, or if its controller has been dropped
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel_receiver.borrow() || self.cancel_receiver.has_changed().is_err()
+    }
+
+    /// Completes once the search has been cancelled, or once its controller has been dropped
+    pub async fn cancelled(&self) {
+        let mut cancel_receiver = self.cancel_receiver.clone();
+        while !*cancel_receiver.borrow_and_update() {
+            if cancel_receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
     /// Returns a copy of the ongoing queries.
     pub async fn query(&self) -> Arc<S::Query> {
         Arc::clone(&self.inner.read().await.query)
@@ -257,6 +512,11 @@ impl<const N: usize, S: Store<N>> OngoingSearchFollower<N, S> {
         self.inner.read().await.config.clone()
     }
 
+    /// Returns the number of results sent to the controler, not counting duplicates.
+    pub async fn results_received(&self) -> usize {
+        self.inner.read().await.results_received
+    }
+
     /// Sets query/peer counts.
     pub async fn set_query_counts(&self, queried_peers: usize, final_peers: usize, ongoing_queries: usize) {
         let mut inner = self.inner.write().await;
@@ -270,19 +530,48 @@ impl<const N: usize, S: Store<N>> Clone for OngoingSearchFollower<N, S> {
     fn clone(&self) -> Self {
         OngoingSearchFollower {
             sender: self.sender.clone(),
+            event_sender: self.event_sender.clone(),
+            cancel_receiver: self.cancel_receiver.clone(),
             inner: Arc::clone(&self.inner),
         }
     }
 }
 
+/// Error returned when sending results to a search that is no longer listened to.
+#[derive(Debug)]
+pub struct SearchInterrupted;
+
+impl std::fmt::Display for SearchInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "search results are no longer listened to")
+    }
+}
+
+impl std::error::Error for SearchInterrupted {}
+
 /// A struct containing search results and some useful information about how the search went.
 #[derive(Debug)]
 pub struct SearchResults<T: SearchResult> {
     /// Contains search results in the order they were received.
     /// Results that have already been [received](OngoingSearchControler::recv) are not included.
     pub hits: Vec<(T, PeerId)>,
+    /// Peers providing each result, when [SearchConfig::deduplicate] is enabled.
+    /// The number of providers of a document can be used as a ranking signal.
+    pub providers: HashMap<T::Cid, Vec<PeerId>>,
     /// Numbers of peers that have been queried
     pub queried_peers: usize,
     /// Numbers of peers that have been able to provide us with hits
     pub final_peers: usize,
 }
+
+impl<T: SearchResult> SearchResults<T> {
+    /// Returns hits along with all the peers known to provide them.
+    /// Without [SearchConfig::deduplicate], each hit only lists the peer that sent it.
+    pub fn hits_with_providers(self) -> Vec<(T, Vec<PeerId>)> {
+        let mut providers = self.providers;
+        self.hits.into_iter().map(|(hit, peer_id)| {
+            let hit_providers = providers.remove(&hit.cid()).unwrap_or_else(|| vec![peer_id]);
+            (hit, hit_providers)
+        }).collect()
+    }
+}
@@ -1,6 +1,38 @@
 use std::collections::BTreeSet;
 use crate::prelude::*;
 
+/// How long a removed seeder is left out of the [candidates](Db::seeder_candidates) to replace it
+const SEEDER_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How long we have been leeching from a seeder and how useful it was
+struct SeederStats {
+    added_at: Instant,
+    /// Number of searches in which routes from this seeder produced results, decaying over time
+    search_hits: f64,
+    /// Alive as long as the task leeching from this seeder holds its [SeederSpot]
+    spot: Weak<()>,
+}
+
+/// Held by the task leeching from a seeder.
+/// The spot can't be [released](Db::remove_seeder) before this is dropped, so that a task ending late doesn't free the spot of the task that replaced it.
+pub(crate) struct SeederSpot {
+    _holder: Arc<()>,
+}
+
+/// A peer we send filters to
+struct Leecher {
+    priority: u32,
+    /// Tells the task seeding to this peer that its spot was taken by a peer with a higher priority
+    preempted: OneshotSender<()>,
+}
+
+/// What we remember about a peer we stopped leeching from
+struct FormerSeeder<const N: usize> {
+    /// The level-0 filter it last sent us
+    filter: Option<Filter<N>>,
+    removed_at: Instant,
+}
+
 pub(crate) struct Db<const N: usize, S: Store<N>> {
     // In order to prevent deadlocks, please lock the different fields in the same order as they are declared in the struct.
 
@@ -9,11 +41,24 @@ pub(crate) struct Db<const N: usize, S: Store<N>> {
     /// Documents to add in the global network corpus
     store: S,
     /// Filters received from seeders
-    seeder_filters: RwLock<BTreeMap<PeerId, Vec<Filter<N>>>>,
+    seeder_filters: RwLock<SeederFilters<N>>,
+    seeder_stats: RwLock<BTreeMap<PeerId, SeederStats>>,
+    /// Connected peers we stopped leeching from
+    former_seeders: RwLock<BTreeMap<PeerId, FormerSeeder<N>>>,
     /// Peers we send filters to
-    leechers: RwLock<BTreeSet<PeerId>>,
+    leechers: RwLock<BTreeMap<PeerId, Leecher>>,
     /// Known addresses of peers that are connected to us
     addrs: RwLock<BTreeMap<PeerId, Vec<Multiaddr>>>,
+    /// Peers that disconnected us, associated with the moment they allowed us to come back (None if never)
+    /// Not async, as the behaviour checks it when dialing.
+    banned_peers: std::sync::RwLock<BTreeMap<PeerId, Option<Instant>>>,
+    /// Peers that refused to seed to us, associated with the moment we may ask them again (None if never)
+    /// Unlike banned peers, we still dial them and route searches through them.
+    postponed_seeders: std::sync::RwLock<BTreeMap<PeerId, Option<Instant>>>,
+    /// Peers whose filters we don't want to hear about, watched by leechers to update their requests
+    blocked_peers: watch::Sender<BTreeSet<PeerId>>,
+    /// Notified when the filter of the store might have changed, watched by seeders to push updates early
+    store_changes: watch::Sender<()>,
 }
 
 impl<const N: usize, S: Store<N>> Db<N, S> {
@@ -22,9 +67,15 @@ impl<const N: usize, S: Store<N>> Db<N, S> {
             config,
             behaviour_controller,
             store,
-            seeder_filters: RwLock::new(BTreeMap::new()),
+            seeder_filters: RwLock::new(SeederFilters::default()),
+            seeder_stats: RwLock::new(BTreeMap::new()),
+            former_seeders: RwLock::new(BTreeMap::new()),
             addrs: RwLock::new(BTreeMap::new()),
-            leechers: RwLock::new(BTreeSet::new()),
+            leechers: RwLock::new(BTreeMap::new()),
+            banned_peers: std::sync::RwLock::new(BTreeMap::new()),
+            postponed_seeders: std::sync::RwLock::new(BTreeMap::new()),
+            blocked_peers: watch::channel(BTreeSet::new()).0,
+            store_changes: watch::channel(()).0,
         }
     }
 
@@ -56,66 +107,247 @@ impl<const N: usize, S: Store<N>> Db<N, S> {
     /// Remove data about a peer.
     pub async fn remove_peer(&self, peer_id: &PeerId) {
         self.seeder_filters.write().await.remove(peer_id);
+        self.seeder_stats.write().await.remove(peer_id);
+        self.former_seeders.write().await.remove(peer_id);
         self.addrs.write().await.remove(peer_id);
         self.leechers.write().await.remove(peer_id);
     }
 
-    /// Claims a spot as a leecher.
-    pub async fn add_leecher(&self, peer_id: PeerId) -> Result<(), TooManyLeechers> {
-        let mut leachers = self.leechers.write().await;
-        if leachers.len() < self.config.max_leechers {
-            leachers.insert(peer_id);
-            Ok(())
-        } else {
-            Err(TooManyLeechers{})
+    /// Prevents us from dialing or leeching from a peer until the delay expires.
+    /// The ban is permanent if `try_again_in` is None.
+    /// An existing ban is only ever extended.
+    pub fn ban_peer(&self, peer_id: PeerId, try_again_in: Option<Duration>) {
+        extend_deadline(&mut self.banned_peers.write().unwrap(), peer_id, try_again_in);
+    }
+
+    /// Returns true if a peer asked us not to come back yet.
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        check_deadline(&mut self.banned_peers.write().unwrap(), peer_id)
+    }
+
+    /// Prevents us from leeching from a peer until the delay expires, without affecting searches.
+    /// This is permanent if `retry_in` is None.
+    /// An existing delay is only ever extended.
+    pub fn postpone_leeching(&self, peer_id: PeerId, retry_in: Option<Duration>) {
+        extend_deadline(&mut self.postponed_seeders.write().unwrap(), peer_id, retry_in);
+    }
+
+    /// Returns true if a peer asked us not to leech from it yet.
+    pub fn is_leeching_postponed(&self, peer_id: &PeerId) -> bool {
+        check_deadline(&mut self.postponed_seeders.write().unwrap(), peer_id)
+    }
+
+    /// Blocks a peer, returning false if it was already blocked.
+    pub fn block_peer(&self, peer_id: PeerId) -> bool {
+        self.blocked_peers.send_if_modified(|blocked_peers| blocked_peers.insert(peer_id))
+    }
+
+    /// Unblocks a peer, returning false if it wasn't blocked.
+    pub fn unblock_peer(&self, peer_id: &PeerId) -> bool {
+        self.blocked_peers.send_if_modified(|blocked_peers| blocked_peers.remove(peer_id))
+    }
+
+    pub fn is_blocked(&self, peer_id: &PeerId) -> bool {
+        self.blocked_peers.borrow().contains(peer_id)
+    }
+
+    pub fn blocked_peers(&self) -> Vec<PeerId> {
+        self.blocked_peers.borrow().iter().copied().collect()
+    }
+
+    /// Returns a receiver notified each time the list of blocked peers changes.
+    pub fn watch_blocked_peers(&self) -> WatchReceiver<BTreeSet<PeerId>> {
+        self.blocked_peers.subscribe()
+    }
+
+    /// Tells seeders that the filter of the store might have changed.
+    pub fn notify_store_changed(&self) {
+        self.store_changes.send_replace(());
+    }
+
+    /// Returns a receiver notified each time [Db::notify_store_changed] is called.
+    pub fn watch_store_changes(&self) -> WatchReceiver<()> {
+        self.store_changes.subscribe()
+    }
+
+    /// Claims a spot as a leecher, preempting the leecher with the lowest priority if all spots are taken by peers with a lower priority.
+    /// The returned receiver resolves if the spot is later taken by another peer.
+    /// If the peer already leeches from us, the spot is handed over to the caller and the previous receiver fails.
+    pub async fn add_leecher(&self, peer_id: PeerId, priority: u32) -> Result<OneshotReceiver<()>, TooManyLeechers> {
+        let mut leechers = self.leechers.write().await;
+        if !leechers.contains_key(&peer_id) && leechers.len() >= self.config.max_leechers {
+            let lowest = leechers.iter().min_by_key(|(_, leecher)| leecher.priority).map(|(peer_id, leecher)| (*peer_id, leecher.priority));
+            match lowest {
+                Some((lowest, lowest_priority)) if lowest_priority < priority => {
+                    if let Some(leecher) = leechers.remove(&lowest) {
+                        let _ = leecher.preempted.send(());
+                    }
+                },
+                _ => return Err(TooManyLeechers{}),
+            }
         }
+        let (sender, receiver) = oneshot_channel();
+        leechers.insert(peer_id, Leecher { priority, preempted: sender });
+        Ok(receiver)
     }
 
     /// Claims a spot as a seeder.
-    pub async fn add_seeder(&self, peer_id: PeerId) -> Result<(), TooManySeeders> {
+    /// If we already leech from this peer, the spot is handed over to the caller.
+    pub async fn add_seeder(&self, peer_id: PeerId) -> Result<SeederSpot, TooManySeeders> {
         let mut seeder_filters = self.seeder_filters.write().await;
-        if seeder_filters.len() < self.config.max_seeders {
-            seeder_filters.insert(peer_id, Vec::new());
-            Ok(())
-        } else {
-            Err(TooManySeeders{})
+        if !seeder_filters.contains_key(&peer_id) && seeder_filters.len() >= self.config.max_seeders {
+            return Err(TooManySeeders{});
+        }
+        let holder = Arc::new(());
+        let mut seeder_stats = self.seeder_stats.write().await;
+        match seeder_stats.get_mut(&peer_id) {
+            Some(stats) => stats.spot = Arc::downgrade(&holder),
+            None => {
+                seeder_filters.insert(peer_id, Vec::new());
+                seeder_stats.insert(peer_id, SeederStats { added_at: Instant::now(), search_hits: 0.0, spot: Arc::downgrade(&holder) });
+            },
         }
+        Ok(SeederSpot { _holder: holder })
     }
 
-    pub async fn set_remote_filter(&self, peer_id: PeerId, filters: Vec<Filter<N>>) {
-        // TODO size checks
-        self.seeder_filters.write().await.insert(peer_id, filters);
+    /// Releases the spot of a leecher.
+    /// Nothing happens if a task still holds the receiver returned by [Db::add_leecher], so that a task ending late doesn't free the spot of the task that replaced it.
+    pub async fn remove_leecher(&self, peer_id: &PeerId) {
+        let mut leechers = self.leechers.write().await;
+        if leechers.get(peer_id).is_some_and(|leecher| !leecher.preempted.is_closed()) {
+            trace!("Not releasing the leecher spot of {peer_id} as a task still holds it");
+            return;
+        }
+        leechers.remove(peer_id);
     }
 
-    pub(crate) async fn get_filters(&self, ignore_peers: &[PeerId]) -> Vec<Filter<N>> {
-        let mut result = Vec::new();
-        result.push(self.store.get_filter().await); // FIXME: This is slow
+    /// Releases the spot of a seeder and forgets its filters, except for its level-0 filter which is kept while it stays connected.
+    /// Nothing happens if a task still holds the [SeederSpot].
+    pub async fn remove_seeder(&self, peer_id: &PeerId) {
+        let mut seeder_filters = self.seeder_filters.write().await;
+        let mut seeder_stats = self.seeder_stats.write().await;
+        if seeder_stats.get(peer_id).is_some_and(|stats| stats.spot.strong_count() > 0) {
+            trace!("Not releasing the seeder spot of {peer_id} as a task still holds it");
+            return;
+        }
+        let Some(filters) = seeder_filters.remove(peer_id) else {
+            return;
+        };
+        seeder_stats.remove(peer_id);
+        drop(seeder_stats);
+        let mut former_seeders = self.former_seeders.write().await;
+        if self.addrs.read().await.contains_key(peer_id) {
+            former_seeders.insert(*peer_id, FormerSeeder {
+                filter: filters.into_iter().next(),
+                removed_at: Instant::now(),
+            });
+        }
+    }
 
-        let filters = self.seeder_filters.read().await;
-        for level in 1..10 {
-            let mut filter = Filter::new();
-            let mut is_null = true;
-            for (peer_id, filters) in filters.iter() {
-                if ignore_peers.contains(peer_id) {
-                    continue;
-                }
-                if let Some(f) = filters.get(level-1) {
-                    filter.bitor_assign_ref(f);
-                    is_null = false;
-                }
+    /// Credits seeders whose routes produced results in a search.
+    pub async fn record_search_hits(&self, seeders: impl IntoIterator<Item = PeerId>) {
+        let mut seeder_stats = self.seeder_stats.write().await;
+        for peer_id in seeders {
+            if let Some(stats) = seeder_stats.get_mut(&peer_id) {
+                stats.search_hits += 1.0;
             }
-            match is_null {
-                true => break,
-                false => result.push(filter),
+        }
+    }
+
+    /// Scores seeders as described in [SeederRotation], returning their score and how long we have been leeching from them.
+    /// Search hits are halved afterwards.
+    pub async fn score_seeders(&self, search_hit_value: f64) -> Vec<(PeerId, f64, Duration)> {
+        let seeder_filters = self.seeder_filters.read().await;
+        let mut seeder_stats = self.seeder_stats.write().await;
+        let mut scores = Vec::with_capacity(seeder_stats.len());
+        for (peer_id, stats) in seeder_stats.iter_mut() {
+            let marginal_bits = seeder_filters.marginal_bits(peer_id, 0);
+            scores.push((*peer_id, marginal_bits as f64 + stats.search_hits * search_hit_value, stats.added_at.elapsed()));
+            stats.search_hits /= 2.0;
+        }
+        scores
+    }
+
+    /// Returns the connected peers we could start leeching from.
+    /// Blocked and banned peers are left out, as well as peers we stopped leeching from recently.
+    pub async fn seeder_candidates(&self) -> Vec<SeederCandidate> {
+        let blocked_peers = self.blocked_peers.borrow().clone();
+        let seeder_filters = self.seeder_filters.read().await;
+        let coverage = seeder_filters.level(0);
+        let former_seeders = self.former_seeders.read().await;
+        let addrs = self.addrs.read().await;
+
+        let mut candidates = Vec::new();
+        for peer_id in addrs.keys() {
+            if seeder_filters.contains_key(peer_id) || blocked_peers.contains(peer_id) {
+                continue;
             }
+            let former_seeder = former_seeders.get(peer_id);
+            if former_seeder.is_some_and(|former_seeder| former_seeder.removed_at.elapsed() < SEEDER_RETRY_DELAY) {
+                continue;
+            }
+            let filter = former_seeder.and_then(|former_seeder| former_seeder.filter.as_ref());
+            candidates.push(SeederCandidate {
+                peer_id: *peer_id,
+                set_bits: filter.map(|filter| filter.count_set_bits()),
+                unique_bits: filter.map(|filter| filter.count_set_bits() - coverage.map(|c| filter.count_common_bits(c)).unwrap_or(0)),
+            });
         }
+        drop((seeder_filters, former_seeders, addrs));
 
-        result
+        candidates.retain(|candidate| !self.is_banned(&candidate.peer_id) && !self.is_leeching_postponed(&candidate.peer_id));
+        candidates
     }
 
-    pub(crate) async fn get_filters_bytes(&self, ignore_peers: &[PeerId]) -> Vec<Vec<u8>> {
-        let filters = self.get_filters(ignore_peers).await;
-        filters.into_iter().map(|f| <Vec<u8>>::from(&f)).collect()
+    /// Replaces the filters of a seeder.
+    /// The filters are left untouched if the new ones are invalid, or more than the `filter_count` we asked for.
+    pub async fn set_remote_filter(&self, peer_id: PeerId, filters: Vec<Filter<N>>, filter_count: usize) -> Result<(), FilterUpdateError> {
+        validate_filters(&filters, filter_count, self.config.max_filter_load)?;
+        self.seeder_filters.write().await.insert(peer_id, filters);
+        Ok(())
+    }
+
+    /// Applies deltas on top of the last filters received from a seeder.
+    /// The filters are left untouched if the deltas don't match them or if the result is invalid.
+    pub async fn apply_remote_filter_deltas(&self, peer_id: PeerId, deltas: Vec<FilterDelta>, filter_count: usize) -> Result<(), FilterUpdateError> {
+        let mut seeder_filters = self.seeder_filters.write().await;
+        let previous_filters = seeder_filters.get(&peer_id).ok_or(FilterUpdateError::DeltaMismatch)?;
+        let mut filters = Vec::with_capacity(deltas.len());
+        for (level, delta) in deltas.into_iter().enumerate() {
+            match delta {
+                FilterDelta::Full(encoded_filter) => filters.push(encoded_filter.decode().map_err(FilterUpdateError::Decoding)?),
+                FilterDelta::Flipped { bits, set_bits } => {
+                    let mut filter = previous_filters.get(level).ok_or(FilterUpdateError::DeltaMismatch)?.clone();
+                    for bit in bits {
+                        filter.flip_bit(bit as usize);
+                    }
+                    if filter.count_set_bits() != set_bits as usize {
+                        return Err(FilterUpdateError::DeltaMismatch);
+                    }
+                    filters.push(filter);
+                }
+            }
+        }
+        validate_filters(&filters, filter_count, self.config.max_filter_load)?;
+        seeder_filters.insert(peer_id, filters);
+        Ok(())
+    }
+
+    /// Returns our filters, from our own documents (level 0) to those of peers at a distance of `filter_count-1`.
+    /// Peers we blocked and peers from `ignore_peers` are left out.
+    pub(crate) async fn get_filters(&self, ignore_peers: &[PeerId], filter_count: usize) -> Vec<Filter<N>> {
+        let mut result = Vec::new();
+        if filter_count == 0 {
+            return result;
+        }
+        result.push(self.store.get_filter().await);
+
+        let mut excluded_peers = self.blocked_peers.borrow().clone();
+        excluded_peers.extend(ignore_peers.iter().copied());
+        let filters = self.seeder_filters.read().await;
+        result.extend(filters.aggregate(&excluded_peers, filter_count - 1));
+
+        result
     }
 
     /// Adds a new address for a peer.
@@ -149,9 +381,11 @@ impl<const N: usize, S: Store<N>> Db<N, S> {
     /// Returns peers and their distance to each query.
     /// Each peer is tested for all its filters, and the matching priorities are returned in an array.
     pub async fn search_routes(&self, query: &S::Query) -> Vec<(PeerId, Vec<u32>)> {
+        let blocked_peers = self.blocked_peers.borrow().clone();
         let filters = self.seeder_filters.read().await;
         filters
             .iter()
+            .filter(|(peer_id, _)| !blocked_peers.contains(peer_id))
             .map(|(peer_id, filters)| {
                 (*peer_id, filters.iter().map(|f| query.match_score(f)).collect::<Vec<_>>())
             })
@@ -178,6 +412,142 @@ impl<const N: usize, S: Store<N>> Db<N, S> {
      */
 }
 
+/// Records a deadline for a peer, keeping the existing one if it is later.
+/// A deadline of None never expires.
+fn extend_deadline(deadlines: &mut BTreeMap<PeerId, Option<Instant>>, peer_id: PeerId, delay: Option<Duration>) {
+    let until = delay.map(|d| Instant::now() + d);
+    match deadlines.get(&peer_id) {
+        Some(None) => (),
+        Some(Some(current)) if until.is_some_and(|until| until <= *current) => (),
+        _ => { deadlines.insert(peer_id, until); },
+    }
+}
+
+/// Returns true if the deadline of a peer hasn't expired yet, forgetting it otherwise.
+fn check_deadline(deadlines: &mut BTreeMap<PeerId, Option<Instant>>, peer_id: &PeerId) -> bool {
+    match deadlines.get(peer_id) {
+        Some(None) => true,
+        Some(Some(until)) if *until > Instant::now() => true,
+        Some(Some(_)) => {
+            deadlines.remove(peer_id);
+            false
+        },
+        None => false,
+    }
+}
+
+/// Checks filters received from a seeder don't exceed the negotiated count, and that its own filter (level 0) isn't overloaded.
+/// Other levels are unions of the filters of many peers, so they are expected to be denser.
+fn validate_filters<const N: usize>(filters: &[Filter<N>], filter_count: usize, max_filter_load: f64) -> Result<(), FilterUpdateError> {
+    if filters.len() > filter_count {
+        return Err(FilterUpdateError::TooManyFilters { max: filter_count, got: filters.len() });
+    }
+    if let Some(filter) = filters.first() {
+        let load = filter.load();
+        if load > max_filter_load {
+            return Err(FilterUpdateError::Overloaded { level: 0, load });
+        }
+    }
+    Ok(())
+}
+
+/// Filters received from seeders, along with their union at each level.
+/// 
+/// The union is maintained incrementally with [CountingFilter]s, so that leaving out the filters of a few peers is cheap.
+#[derive(Default)]
+pub(crate) struct SeederFilters<const N: usize> {
+    filters: BTreeMap<PeerId, Vec<Filter<N>>>,
+    /// Union of the filters of all seeders at each level
+    aggregate: Vec<CountingFilter<N>>,
+    /// Number of seeders having a filter at each level
+    level_counts: Vec<usize>,
+}
+
+impl<const N: usize> SeederFilters<N> {
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn contains_key(&self, peer_id: &PeerId) -> bool {
+        self.filters.contains_key(peer_id)
+    }
+
+    pub fn get(&self, peer_id: &PeerId) -> Option<&Vec<Filter<N>>> {
+        self.filters.get(peer_id)
+    }
+
+    /// Returns the number of bits set in the filter of a seeder at a level that no other seeder has set.
+    pub fn marginal_bits(&self, peer_id: &PeerId, level: usize) -> usize {
+        let Some(filter) = self.filters.get(peer_id).and_then(|filters| filters.get(level)) else {
+            return 0;
+        };
+        filter.iter_set_bits().filter(|bit| self.aggregate[level].get_count(*bit) == 1).count()
+    }
+
+    /// Returns the union of the filters of all seeders at a level.
+    pub fn level(&self, level: usize) -> Option<&Filter<N>> {
+        self.aggregate.get(level).map(|filter| filter.as_filter())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PeerId, &Vec<Filter<N>>)> {
+        self.filters.iter()
+    }
+
+    pub fn insert(&mut self, peer_id: PeerId, filters: Vec<Filter<N>>) {
+        self.remove(&peer_id);
+        for (level, filter) in filters.iter().enumerate() {
+            if level >= self.aggregate.len() {
+                self.aggregate.push(CountingFilter::new());
+                self.level_counts.push(0);
+            }
+            self.aggregate[level].add_filter(filter);
+            self.level_counts[level] += 1;
+        }
+        self.filters.insert(peer_id, filters);
+    }
+
+    pub fn remove(&mut self, peer_id: &PeerId) -> Option<Vec<Filter<N>>> {
+        let filters = self.filters.remove(peer_id)?;
+        for (level, filter) in filters.iter().enumerate() {
+            self.aggregate[level].remove_filter(filter);
+            self.level_counts[level] -= 1;
+        }
+        while self.level_counts.last() == Some(&0) {
+            self.aggregate.pop();
+            self.level_counts.pop();
+        }
+        Some(filters)
+    }
+
+    /// Returns the union of the filters of seeders at each level, up to `max_levels` levels, leaving `excluded_peers` out.
+    /// Stops at the first level no remaining seeder has a filter for.
+    pub fn aggregate(&self, excluded_peers: &BTreeSet<PeerId>, max_levels: usize) -> Vec<Filter<N>> {
+        let excluded_filters = excluded_peers.iter().filter_map(|peer_id| self.filters.get(peer_id)).collect::<Vec<_>>();
+        let mut result = Vec::new();
+        for (level, aggregate) in self.aggregate.iter().enumerate().take(max_levels) {
+            let excluded_at_level = excluded_filters.iter().filter_map(|filters| filters.get(level)).collect::<Vec<_>>();
+            if self.level_counts[level] == excluded_at_level.len() {
+                break;
+            }
+
+            // Bits only set by excluded peers are cleared
+            let mut filter = aggregate.as_filter().clone();
+            let mut excluded_counts: HashMap<usize, usize> = HashMap::new();
+            for excluded_filter in excluded_at_level {
+                excluded_filter.iter_set_bits().for_each(|idx| *excluded_counts.entry(idx).or_default() += 1);
+            }
+            for (idx, count) in excluded_counts {
+                let total = aggregate.get_count(idx);
+                if total != u16::MAX && total as usize <= count {
+                    filter.set_bit(idx, false);
+                }
+            }
+            result.push(filter);
+        }
+        result
+    }
+}
+
 /// Error returned when we try to add a new leecher but there are already too many.
 #[derive(Debug, Clone)]
 pub struct TooManyLeechers {}
@@ -186,6 +556,167 @@ pub struct TooManyLeechers {}
 #[derive(Debug, Clone)]
 pub struct TooManySeeders {}
 
+/// Error returned when filters sent by a seeder are rejected.
+#[derive(Debug, Clone)]
+pub enum FilterUpdateError {
+    /// The deltas can't be applied to the filters we know.
+    /// This is not the seeder's fault and is fixed by asking for a full update.
+    DeltaMismatch,
+    /// A filter couldn't be decoded.
+    Decoding(FilterDecodingError),
+    /// More filters were sent than negotiated.
+    TooManyFilters { max: usize, got: usize },
+    /// A filter has more bits set than [KamilataConfig::max_filter_load] allows.
+    /// This is typical of filters trying to match every query.
+    Overloaded { level: usize, load: f64 },
+    /// Updates are sent more often than negotiated.
+    TooFrequent { elapsed_ms: usize, min_ms: usize },
+}
+
+impl std::fmt::Display for FilterUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterUpdateError::DeltaMismatch => write!(f, "filter deltas don't match previous filters"),
+            FilterUpdateError::Decoding(e) => write!(f, "{e}"),
+            FilterUpdateError::TooManyFilters { max, got } => write!(f, "too many filters (max {max}, got {got})"),
+            FilterUpdateError::Overloaded { level, load } => write!(f, "filter at level {level} is overloaded ({:.2}% of bits set)", load * 100.0),
+            FilterUpdateError::TooFrequent { elapsed_ms, min_ms } => write!(f, "filters updated too often ({elapsed_ms}ms since last update, min {min_ms}ms)"),
+        }
+    }
+}
+
 /// Error returned when we try an operation on a peer that is not connected to us.
 #[derive(Debug, Clone)]
 pub struct DisconnectedPeer;
+
+/// Error returned when connecting to a peer that asked us not to come back yet.
+#[derive(Debug, Clone)]
+pub struct BannedPeer;
+
+impl std::fmt::Display for BannedPeer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the peer asked us not to come back yet")
+    }
+}
+
+impl std::error::Error for BannedPeer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn naive_aggregate(filters: &BTreeMap<PeerId, Vec<Filter<16>>>, excluded_peers: &BTreeSet<PeerId>, max_levels: usize) -> Vec<Filter<16>> {
+        let mut result = Vec::new();
+        for level in 0..max_levels {
+            let mut filter = Filter::new();
+            let mut is_null = true;
+            for (_, filters) in filters.iter().filter(|(peer_id, _)| !excluded_peers.contains(peer_id)) {
+                if let Some(f) = filters.get(level) {
+                    filter.bitor_assign_ref(f);
+                    is_null = false;
+                }
+            }
+            match is_null {
+                true => break,
+                false => result.push(filter),
+            }
+        }
+        result
+    }
+
+    fn random_filters(rng: &mut StdRng) -> Vec<Filter<16>> {
+        (0..rng.gen_range(0..5)).map(|_| {
+            let mut filter = Filter::new();
+            for _ in 0..20 {
+                filter.set_bit(rng.gen_range(0..128), true);
+            }
+            filter
+        }).collect()
+    }
+
+    #[test]
+    fn seeder_filters_aggregate() {
+        let peers = (0..8).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let mut expected = BTreeMap::new();
+        let mut seeder_filters = SeederFilters::<16>::default();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..200 {
+            let peer_id = peers[rng.gen_range(0..peers.len())];
+            match rng.gen_range(0..3) {
+                0 => {
+                    expected.remove(&peer_id);
+                    seeder_filters.remove(&peer_id);
+                },
+                _ => {
+                    let filters = random_filters(&mut rng);
+                    expected.insert(peer_id, filters.clone());
+                    seeder_filters.insert(peer_id, filters);
+                },
+            }
+
+            let excluded_peers = peers.iter().filter(|_| rng.gen_bool(0.25)).copied().collect::<BTreeSet<_>>();
+            for max_levels in [0, 2, 8] {
+                assert_eq!(seeder_filters.aggregate(&excluded_peers, max_levels), naive_aggregate(&expected, &excluded_peers, max_levels));
+            }
+        }
+    }
+
+    #[test]
+    fn deadlines() {
+        let peer_id = PeerId::random();
+        let mut deadlines = BTreeMap::new();
+        assert!(!check_deadline(&mut deadlines, &peer_id));
+
+        extend_deadline(&mut deadlines, peer_id, Some(Duration::from_secs(60)));
+        extend_deadline(&mut deadlines, peer_id, Some(Duration::ZERO));
+        assert!(check_deadline(&mut deadlines, &peer_id));
+
+        // Permanent deadlines are never shortened
+        extend_deadline(&mut deadlines, peer_id, None);
+        extend_deadline(&mut deadlines, peer_id, Some(Duration::from_secs(3600)));
+        assert_eq!(deadlines.get(&peer_id), Some(&None));
+
+        let other_peer_id = PeerId::random();
+        extend_deadline(&mut deadlines, other_peer_id, Some(Duration::ZERO));
+        assert!(!check_deadline(&mut deadlines, &other_peer_id));
+        assert!(!deadlines.contains_key(&other_peer_id));
+    }
+
+    #[test]
+    fn filter_validation() {
+        let filter = |load: usize| {
+            let mut filter = Filter::<16>::new();
+            (0..load).for_each(|bit| filter.set_bit(bit, true));
+            filter
+        };
+        assert!(validate_filters(&[filter(20), filter(50)], 2, 0.5).is_ok());
+        assert!(matches!(validate_filters(&[filter(20), filter(50)], 1, 0.5), Err(FilterUpdateError::TooManyFilters { max: 1, got: 2 })));
+        assert!(matches!(validate_filters(&[filter(100)], 1, 0.5), Err(FilterUpdateError::Overloaded { level: 0, .. })));
+
+        // Filters at a distance aggregate many peers so they can legitimately be dense
+        assert!(validate_filters(&[filter(20), filter(80), filter(120)], 3, 0.5).is_ok());
+    }
+
+    #[test]
+    fn marginal_bits() {
+        let filter = |bits: &[usize]| {
+            let mut filter = Filter::<16>::new();
+            bits.iter().for_each(|bit| filter.set_bit(*bit, true));
+            filter
+        };
+        let (peer1, peer2) = (PeerId::random(), PeerId::random());
+        let mut seeder_filters = SeederFilters::<16>::default();
+        seeder_filters.insert(peer1, vec![filter(&[1, 2, 3]), filter(&[10])]);
+        seeder_filters.insert(peer2, vec![filter(&[3, 4])]);
+        assert_eq!(seeder_filters.marginal_bits(&peer1, 0), 2);
+        assert_eq!(seeder_filters.marginal_bits(&peer2, 0), 1);
+        assert_eq!(seeder_filters.marginal_bits(&peer1, 1), 1);
+        assert_eq!(seeder_filters.marginal_bits(&peer2, 1), 0);
+
+        seeder_filters.remove(&peer1);
+        assert_eq!(seeder_filters.marginal_bits(&peer2, 0), 2);
+        assert_eq!(seeder_filters.marginal_bits(&peer1, 0), 0);
+    }
+}
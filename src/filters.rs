@@ -1,15 +1,23 @@
-#[derive(Debug, Clone)]
-pub struct Filter<const N: usize>(Box<[u8; N]>);
+/// A Bloom filter of `N` bytes.
+/// 
+/// Bits are stored in 64-bit words so that bulk operations process 64 bits at once.
+/// Bit `i` is bit `i % 8` of byte `i / 8` in the byte representation used on the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter<const N: usize>(Box<[u64]>);
 
 impl<const N: usize> Filter<N> {
+    /// Number of 64-bit words needed to store `N` bytes.
+    /// Bits past `N*8` in the last word are always zero.
+    const WORDS: usize = N.div_ceil(8);
+
     /// Creates an empty filter.
     pub fn new() -> Self {
-        Filter(Box::new([0; N]))
+        Filter(vec![0; Self::WORDS].into_boxed_slice())
     }
 
     /// Clears the filter.
     pub fn clear(&mut self) {
-        *self = Self::new();
+        self.0.fill(0);
     }
 
     /// Gets a bit in the filter.
@@ -17,12 +25,7 @@ impl<const N: usize> Filter<N> {
         if idx >= self.bit_len() {
             return false;
         }
-        let bit_idx = idx.rem_euclid(8);
-        let byte_idx = idx.div_euclid(8);
-        let bit = unsafe {
-            (self.0.get_unchecked(byte_idx) >> bit_idx) & 1
-        };
-        bit != 0
+        (self.0[idx / 64] >> (idx % 64)) & 1 != 0
     }
 
     /// Gets a word in the filter.
@@ -35,23 +38,60 @@ impl<const N: usize> Filter<N> {
         if idx >= self.bit_len() {
             return;
         }
-        let bit_idx = idx.rem_euclid(8);
-        let byte_idx = idx.div_euclid(8);
-        let bit = value as u8;
-        let keeping_mask = !(1 << bit_idx);
-        unsafe {
-            *self.0.get_unchecked_mut(byte_idx) = (self.0.get_unchecked(byte_idx) & keeping_mask) + (bit << bit_idx);
+        let mask = 1 << (idx % 64);
+        match value {
+            true => self.0[idx / 64] |= mask,
+            false => self.0[idx / 64] &= !mask,
+        }
+    }
+
+    /// Flips a bit in the filter.
+    pub fn flip_bit(&mut self, idx: usize) {
+        if idx < self.bit_len() {
+            self.0[idx / 64] ^= 1 << (idx % 64);
         }
     }
 
     /// Adds a word in the filter.
+    /// The word is hashed as is, see [Filter::add_text] to add words as the store [tokenizes](crate::store::Store::TOKENIZER) them.
     pub fn add_word<S: crate::store::Store<N>>(&mut self, word: &str) {
         S::hash_word(word).into_iter().for_each(|hash| self.set_bit(hash, true));
     }
 
+    /// Splits text into words with the store's tokenizer and adds them in the filter.
+    pub fn add_text<S: crate::store::Store<N>>(&mut self, text: &str) {
+        S::TOKENIZER.tokenize(text).iter().for_each(|word| self.add_word::<S>(word));
+    }
+
+    /// Returns true if all the bits at these indices are set.
+    /// Out of range indices are considered unset.
+    pub fn contains_all(&self, indices: &[usize]) -> bool {
+        indices.iter().all(|idx| self.get_bit(*idx))
+    }
+
     /// Returns the number of bits set to 1 in the filter.
     pub fn count_set_bits(&self) -> usize {
-        self.0.iter().map(|byte| byte.count_ones() as usize).sum()
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns the number of bits set to 1 in both filters, without building their intersection.
+    pub fn count_common_bits(&self, other: &Self) -> usize {
+        self.0.iter().zip(other.0.iter()).map(|(a, b)| (a & b).count_ones() as usize).sum()
+    }
+
+    /// Iterates over the indices of the bits set to 1, in increasing order.
+    pub fn iter_set_bits(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().filter(|(_, word)| **word != 0).flat_map(|(word_idx, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit_idx = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(word_idx * 64 + bit_idx)
+            })
+        })
     }
 
     /// Returns the proportion of bits that are set to 1.
@@ -71,7 +111,104 @@ impl<const N: usize> Filter<N> {
 
     /// Returns true if the filter is empty.
     pub fn is_empty(&self) -> bool {
-        self.0.iter().all(|byte| *byte == 0)
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    /// Returns the indices of the bits that differ between the two filters.
+    pub fn diff(&self, other: &Self) -> Vec<usize> {
+        let mut indices = Vec::new();
+        for (word_idx, (a, b)) in self.0.iter().zip(other.0.iter()).enumerate() {
+            let mut xor = a ^ b;
+            while xor != 0 {
+                let bit_idx = xor.trailing_zeros() as usize;
+                indices.push(word_idx * 64 + bit_idx);
+                xor &= xor - 1;
+            }
+        }
+        indices
+    }
+
+    /// Applies an operation on each word of the two filters.
+    #[inline]
+    fn apply(&mut self, other: &Self, op: impl Fn(u64, u64) -> u64) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a = op(*a, *b);
+        }
+    }
+
+    /// Sets the bits that are set in `other`.
+    pub fn bitor_assign_ref(&mut self, other: &Self) {
+        self.apply(other, |a, b| a | b);
+    }
+
+    /// Clears the bits that are not set in `other`.
+    pub fn bitand_assign_ref(&mut self, other: &Self) {
+        self.apply(other, |a, b| a & b);
+    }
+
+    /// Flips the bits that are set in `other`.
+    pub fn bitxor_assign_ref(&mut self, other: &Self) {
+        self.apply(other, |a, b| a ^ b);
+    }
+
+    /// Clears the bits that are set in `other`.
+    pub fn and_not_assign_ref(&mut self, other: &Self) {
+        self.apply(other, |a, b| a & !b);
+    }
+
+    /// Returns the bits that are set in `self` but not in `other`.
+    pub fn and_not(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.and_not_assign_ref(other);
+        result
+    }
+
+    /// Returns true if every bit set in `self` is also set in `other`.
+    pub fn is_subset_of(&self, other: &Self) -> bool {
+        self.0.iter().zip(other.0.iter()).all(|(a, b)| a & !b == 0)
+    }
+
+    /// Returns the Jaccard similarity of the bits set in both filters, between 0 and 1.
+    /// Two empty filters are considered identical.
+    pub fn jaccard_similarity(&self, other: &Self) -> f64 {
+        let union: usize = self.0.iter().zip(other.0.iter()).map(|(a, b)| (a | b).count_ones() as usize).sum();
+        if union == 0 {
+            return 1.0;
+        }
+        self.count_common_bits(other) as f64 / union as f64
+    }
+
+    /// Estimates the number of distinct words that were added to the filter, given the number of hashes per word.
+    /// Uses `-m/k · ln(1 - X/m)` where `m` is the number of bits and `X` the number of set bits.
+    /// Returns infinity when all bits are set.
+    pub fn estimated_len(&self, hash_count: usize) -> f64 {
+        let m = self.bit_len() as f64;
+        let x = self.count_set_bits() as f64;
+        -m / hash_count as f64 * (1.0 - x / m).ln()
+    }
+
+    /// Returns the probability that a word that was never added is reported as present, given the number of hashes per word.
+    pub fn false_positive_rate(&self, hash_count: usize) -> f64 {
+        false_positive_rate(self.load(), hash_count)
+    }
+
+    /// Returns the byte representation of the filter.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.0.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<_>>();
+        bytes.truncate(N);
+        bytes
+    }
+
+    /// Builds a filter from its byte representation, which must be exactly `N` bytes long.
+    fn from_bytes_exact(bytes: &[u8]) -> Self {
+        debug_assert_eq!(bytes.len(), N);
+        let mut filter = Filter::new();
+        for (word, chunk) in filter.0.iter_mut().zip(bytes.chunks(8)) {
+            let mut word_bytes = [0; 8];
+            word_bytes[..chunk.len()].copy_from_slice(chunk);
+            *word = u64::from_le_bytes(word_bytes);
+        }
+        filter
     }
 }
 
@@ -85,47 +222,375 @@ impl<const N: usize> std::ops::BitOr for Filter<N> {
     type Output = Self;
 
     fn bitor(mut self, other: Self) -> Self::Output {
-        for byte_idx in 0..N {
-            unsafe {
-                *self.0.get_unchecked_mut(byte_idx) |= *other.0.get_unchecked(byte_idx);
-            }
-        }
+        self.bitor_assign_ref(&other);
         self
     }
 }
 
-impl<const N: usize> Filter<N> {
-    pub fn bitor_assign_ref(&mut self, other: &Self) {
-        for byte_idx in 0..N {
-            unsafe {
-                *self.0.get_unchecked_mut(byte_idx) |= *other.0.get_unchecked(byte_idx);
-            }
+impl<const N: usize> std::ops::BitOrAssign for Filter<N> {
+    fn bitor_assign(&mut self, other: Self) {
+        self.bitor_assign_ref(&other);
+    }
+}
+
+impl<const N: usize> std::ops::BitAnd for Filter<N> {
+    type Output = Self;
+
+    fn bitand(mut self, other: Self) -> Self::Output {
+        self.bitand_assign_ref(&other);
+        self
+    }
+}
+
+impl<const N: usize> std::ops::BitAndAssign for Filter<N> {
+    fn bitand_assign(&mut self, other: Self) {
+        self.bitand_assign_ref(&other);
+    }
+}
+
+impl<const N: usize> std::ops::BitXor for Filter<N> {
+    type Output = Self;
+
+    fn bitxor(mut self, other: Self) -> Self::Output {
+        self.bitxor_assign_ref(&other);
+        self
+    }
+}
+
+impl<const N: usize> std::ops::BitXorAssign for Filter<N> {
+    fn bitxor_assign(&mut self, other: Self) {
+        self.bitxor_assign_ref(&other);
+    }
+}
+
+/// Returns the probability that a Bloom filter with this proportion of set bits reports a word that was never added, given the number of hashes per word.
+pub fn false_positive_rate(load: f64, hash_count: usize) -> f64 {
+    load.powi(hash_count as i32)
+}
+
+/// A Bloom filter keeping a counter for each bit, so that words can be removed.
+/// 
+/// Only its projection to a [Filter] is advertised to other peers.
+/// The projection is maintained incrementally, so [CountingFilter::as_filter] is cheap.
+/// 
+/// Counters saturate at [u16::MAX] and are then never decremented, as the actual count is unknown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountingFilter<const N: usize> {
+    counters: Box<[u16]>,
+    filter: Filter<N>,
+}
+
+impl<const N: usize> CountingFilter<N> {
+    /// Creates an empty filter.
+    pub fn new() -> Self {
+        CountingFilter {
+            counters: vec![0; N*8].into_boxed_slice(),
+            filter: Filter::new(),
+        }
+    }
+
+    /// Gets the counter of a bit.
+    pub fn get_count(&self, idx: usize) -> u16 {
+        self.counters.get(idx).copied().unwrap_or(0)
+    }
+
+    /// Increments the counter of a bit.
+    pub fn increment(&mut self, idx: usize) {
+        let Some(counter) = self.counters.get_mut(idx) else {return};
+        *counter = counter.saturating_add(1);
+        self.filter.set_bit(idx, true);
+    }
+
+    /// Decrements the counter of a bit, unless it is saturated.
+    pub fn decrement(&mut self, idx: usize) {
+        let Some(counter) = self.counters.get_mut(idx) else {return};
+        if *counter == 0 || *counter == u16::MAX {
+            return;
+        }
+        *counter -= 1;
+        if *counter == 0 {
+            self.filter.set_bit(idx, false);
         }
     }
+
+    /// Gets a word in the filter.
+    pub fn get_word<S: crate::store::Store<N>>(&self, word: &str) -> bool {
+        self.filter.get_word::<S>(word)
+    }
+
+    /// Adds a word in the filter.
+    /// Adding the same word multiple times requires removing it as many times.
+    pub fn add_word<S: crate::store::Store<N>>(&mut self, word: &str) {
+        S::hash_word(word).into_iter().for_each(|hash| self.increment(hash));
+    }
+
+    /// Removes a word from the filter.
+    /// The word must have been [added](CountingFilter::add_word) before, otherwise other words could be removed too.
+    pub fn remove_word<S: crate::store::Store<N>>(&mut self, word: &str) {
+        S::hash_word(word).into_iter().for_each(|hash| self.decrement(hash));
+    }
+
+    /// Splits text into words with the store's tokenizer and adds them in the filter.
+    pub fn add_text<S: crate::store::Store<N>>(&mut self, text: &str) {
+        S::TOKENIZER.tokenize(text).iter().for_each(|word| self.add_word::<S>(word));
+    }
+
+    /// Removes the words of a text that was [added](CountingFilter::add_text) before.
+    pub fn remove_text<S: crate::store::Store<N>>(&mut self, text: &str) {
+        S::TOKENIZER.tokenize(text).iter().for_each(|word| self.remove_word::<S>(word));
+    }
+
+    /// Adds all the bits of a filter.
+    pub fn add_filter(&mut self, filter: &Filter<N>) {
+        filter.iter_set_bits().for_each(|idx| self.increment(idx));
+    }
+
+    /// Removes all the bits of a filter that was [added](CountingFilter::add_filter) before.
+    pub fn remove_filter(&mut self, filter: &Filter<N>) {
+        filter.iter_set_bits().for_each(|idx| self.decrement(idx));
+    }
+
+    /// Returns the filter with bits set where counters are not zero.
+    pub fn as_filter(&self) -> &Filter<N> {
+        &self.filter
+    }
+
+    /// Returns true if the filter is empty.
+    pub fn is_empty(&self) -> bool {
+        self.filter.is_empty()
+    }
 }
 
-impl<const N: usize> std::ops::BitOrAssign for Filter<N> {
-    fn bitor_assign(&mut self, other: Self) {
-        self.bitor_assign_ref(&other);
+impl<const N: usize> Default for CountingFilter<N> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+impl<const N: usize> From<CountingFilter<N>> for Filter<N> {
+    fn from(counting_filter: CountingFilter<N>) -> Self {
+        counting_filter.filter
+    }
+}
+
+/// Missing bytes are considered to be zeros and extra bytes are ignored.
+/// Use [Filter::decode] to reject invalid data instead.
 impl<const N: usize> From<&[u8]> for Filter<N> {
     fn from(bytes: &[u8]) -> Self {
-        let mut filter = Filter::new();
-        for byte_idx in 0..N {
-            // TODO check that bytes.len() == N
-            unsafe {
-                *filter.0.get_unchecked_mut(byte_idx) = *bytes.get_unchecked(byte_idx);
-            }
-        }
-        filter
+        let mut padded = vec![0; N];
+        let len = bytes.len().min(N);
+        padded[..len].copy_from_slice(&bytes[..len]);
+        Filter::from_bytes_exact(&padded)
     }
 }
 
 impl<const N: usize> From<&Filter<N>> for Vec<u8> {
     fn from(filter: &Filter<N>) -> Self {
-        filter.0.to_vec()
+        filter.to_bytes()
+    }
+}
+
+/// Number of bytes in a chunk of the [FilterEncoding::Bitmap] encoding (65536 bits).
+const BITMAP_CHUNK_LEN: usize = 8192;
+
+/// Ways a filter can be encoded to be sent over the network.
+#[derive(protocol::Protocol, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterEncoding {
+    /// The bytes of the filter, as is.
+    Raw,
+    /// Alternating runs of zero bytes and literal bytes.
+    /// Efficient for filters containing a few words.
+    RunLength,
+    /// Roaring-style compressed bitmap.
+    /// The filter is split in chunks of 65536 bits that are either sent as a sorted list of set bits or as is.
+    Bitmap,
+}
+
+impl FilterEncoding {
+    /// Returns all the encodings supported by this implementation.
+    pub fn all() -> Vec<FilterEncoding> {
+        vec![FilterEncoding::Raw, FilterEncoding::RunLength, FilterEncoding::Bitmap]
+    }
+}
+
+/// Error returned when bytes can't be decoded into a filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecodingError {
+    /// The decoded filter doesn't have the expected number of bytes.
+    InvalidLength { expected: usize, got: usize },
+    /// The data ended unexpectedly.
+    Truncated,
+    /// The data is not a valid encoding of a filter.
+    Malformed,
+}
+
+impl std::fmt::Display for FilterDecodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterDecodingError::InvalidLength { expected, got } => write!(f, "invalid filter length (expected {expected} bytes, got {got})"),
+            FilterDecodingError::Truncated => write!(f, "truncated filter data"),
+            FilterDecodingError::Malformed => write!(f, "malformed filter data"),
+        }
+    }
+}
+
+impl std::error::Error for FilterDecodingError {}
+
+pub(crate) fn push_varint(buffer: &mut Vec<u8>, value: usize) {
+    let mut varint_buffer = unsigned_varint::encode::usize_buffer();
+    buffer.extend_from_slice(unsigned_varint::encode::usize(value, &mut varint_buffer));
+}
+
+pub(crate) fn read_varint(data: &mut &[u8]) -> Result<usize, FilterDecodingError> {
+    let (value, remaining) = unsigned_varint::decode::usize(data).map_err(|e| match e {
+        unsigned_varint::decode::Error::Insufficient => FilterDecodingError::Truncated,
+        _ => FilterDecodingError::Malformed,
+    })?;
+    *data = remaining;
+    Ok(value)
+}
+
+pub(crate) fn read_bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], FilterDecodingError> {
+    if data.len() < len {
+        return Err(FilterDecodingError::Truncated);
+    }
+    let (bytes, remaining) = data.split_at(len);
+    *data = remaining;
+    Ok(bytes)
+}
+
+impl<const N: usize> Filter<N> {
+    /// Encodes the filter to be sent over the network.
+    pub fn encode(&self, encoding: FilterEncoding) -> Vec<u8> {
+        let bytes = self.to_bytes();
+        match encoding {
+            FilterEncoding::Raw => bytes,
+            FilterEncoding::RunLength => {
+                let mut buffer = Vec::new();
+                let mut i = 0;
+                while i < N {
+                    let zeros_start = i;
+                    while i < N && bytes[i] == 0 {
+                        i += 1;
+                    }
+                    let literal_start = i;
+                    // Literals end on two consecutive zeros, as a lonely zero is cheaper to keep in the literal
+                    while i < N && !(bytes[i] == 0 && bytes.get(i + 1).map(|b| *b == 0).unwrap_or(true)) {
+                        i += 1;
+                    }
+                    push_varint(&mut buffer, literal_start - zeros_start);
+                    push_varint(&mut buffer, i - literal_start);
+                    buffer.extend_from_slice(&bytes[literal_start..i]);
+                }
+                buffer
+            },
+            FilterEncoding::Bitmap => {
+                let mut chunks = Vec::new();
+                for (chunk_idx, chunk) in bytes.chunks(BITMAP_CHUNK_LEN).enumerate() {
+                    let set_bits: usize = chunk.iter().map(|byte| byte.count_ones() as usize).sum();
+                    if set_bits > 0 {
+                        chunks.push((chunk_idx, chunk, set_bits));
+                    }
+                }
+
+                let mut buffer = Vec::new();
+                push_varint(&mut buffer, chunks.len());
+                for (chunk_idx, chunk, set_bits) in chunks {
+                    push_varint(&mut buffer, chunk_idx);
+                    if set_bits * 2 < chunk.len() {
+                        buffer.push(0);
+                        push_varint(&mut buffer, set_bits);
+                        for (byte_idx, byte) in chunk.iter().enumerate().filter(|(_, byte)| **byte != 0) {
+                            let mut byte = *byte;
+                            while byte != 0 {
+                                let bit_idx = byte.trailing_zeros() as usize;
+                                buffer.extend_from_slice(&((byte_idx * 8 + bit_idx) as u16).to_le_bytes());
+                                byte &= byte - 1;
+                            }
+                        }
+                    } else {
+                        buffer.push(1);
+                        buffer.extend_from_slice(chunk);
+                    }
+                }
+                buffer
+            },
+        }
+    }
+
+    /// Decodes a filter received from the network.
+    /// Unlike [Filter::from], this checks the data is valid.
+    pub fn decode(data: &[u8], encoding: FilterEncoding) -> Result<Self, FilterDecodingError> {
+        let mut bytes = vec![0; N];
+        let mut data = data;
+        match encoding {
+            FilterEncoding::Raw => {
+                if data.len() != N {
+                    return Err(FilterDecodingError::InvalidLength { expected: N, got: data.len() });
+                }
+                bytes.copy_from_slice(data);
+            },
+            FilterEncoding::RunLength => {
+                let mut i: usize = 0;
+                while !data.is_empty() {
+                    let zeros = read_varint(&mut data)?;
+                    let literal_len = read_varint(&mut data)?;
+                    let literal = read_bytes(&mut data, literal_len)?;
+                    // Lengths come from the remote peer and must not be trusted not to overflow
+                    let Some(literal_start) = i.checked_add(zeros) else {
+                        return Err(FilterDecodingError::Malformed);
+                    };
+                    let Some(end) = literal_start.checked_add(literal_len) else {
+                        return Err(FilterDecodingError::Malformed);
+                    };
+                    if end > N {
+                        return Err(FilterDecodingError::InvalidLength { expected: N, got: end });
+                    }
+                    bytes[literal_start..end].copy_from_slice(literal);
+                    i = end;
+                }
+                if i != N {
+                    return Err(FilterDecodingError::InvalidLength { expected: N, got: i });
+                }
+            },
+            FilterEncoding::Bitmap => {
+                let chunk_count = read_varint(&mut data)?;
+                let max_chunk_count = N.div_ceil(BITMAP_CHUNK_LEN);
+                if chunk_count > max_chunk_count {
+                    return Err(FilterDecodingError::Malformed);
+                }
+                let mut min_chunk_idx = 0;
+                for _ in 0..chunk_count {
+                    let chunk_idx = read_varint(&mut data)?;
+                    if chunk_idx < min_chunk_idx || chunk_idx >= max_chunk_count {
+                        return Err(FilterDecodingError::Malformed);
+                    }
+                    min_chunk_idx = chunk_idx + 1;
+                    let start = chunk_idx * BITMAP_CHUNK_LEN;
+                    let chunk = &mut bytes[start..(start + BITMAP_CHUNK_LEN).min(N)];
+                    match read_bytes(&mut data, 1)?[0] {
+                        0 => {
+                            let set_bits = read_varint(&mut data)?;
+                            let positions = read_bytes(&mut data, set_bits.checked_mul(2).ok_or(FilterDecodingError::Malformed)?)?;
+                            for position in positions.chunks_exact(2) {
+                                let bit = u16::from_le_bytes([position[0], position[1]]) as usize;
+                                let byte = chunk.get_mut(bit / 8).ok_or(FilterDecodingError::Malformed)?;
+                                *byte |= 1 << (bit % 8);
+                            }
+                        },
+                        1 => {
+                            let len = chunk.len();
+                            chunk.copy_from_slice(read_bytes(&mut data, len)?);
+                        },
+                        _ => return Err(FilterDecodingError::Malformed),
+                    }
+                }
+                if !data.is_empty() {
+                    return Err(FilterDecodingError::Malformed);
+                }
+            },
+        }
+        Ok(Filter::from_bytes_exact(&bytes))
     }
 }
 
@@ -146,6 +611,56 @@ mod tests {
         assert_eq!(filter.count_set_bits(), 2);
     }
 
+    #[test]
+    fn counting_filter() {
+        let mut filter = CountingFilter::<4>::new();
+        filter.increment(8);
+        filter.increment(8);
+        filter.increment(9);
+        assert_eq!(filter.get_count(8), 2);
+        assert_eq!(filter.as_filter().count_set_bits(), 2);
+        filter.decrement(8);
+        filter.decrement(9);
+        assert!(filter.as_filter().get_bit(8));
+        assert!(!filter.as_filter().get_bit(9));
+        filter.decrement(9);
+        assert_eq!(filter.get_count(9), 0);
+        filter.decrement(8);
+        assert!(filter.is_empty());
+
+        // Counters go well beyond the number of seeders or documents sharing a bit
+        for _ in 0..300 {
+            filter.increment(10);
+        }
+        assert_eq!(filter.get_count(10), 300);
+        for _ in 0..300 {
+            filter.decrement(10);
+        }
+        assert!(filter.is_empty());
+
+        // Saturated counters are never decremented
+        filter.counters[10] = u16::MAX;
+        filter.decrement(10);
+        assert_eq!(filter.get_count(10), u16::MAX);
+        filter.counters[10] = 0;
+        filter.filter.set_bit(10, false);
+
+        // Out of range indices are ignored
+        filter.increment(32);
+        assert_eq!(filter.get_count(32), 0);
+
+        let mut bits = Filter::<4>::new();
+        bits.set_bit(3, true);
+        bits.set_bit(31, true);
+        assert_eq!(bits.iter_set_bits().collect::<Vec<_>>(), vec![3, 31]);
+        filter.add_filter(&bits);
+        filter.add_filter(&bits);
+        filter.remove_filter(&bits);
+        assert_eq!(filter.get_count(3), 1);
+        filter.remove_filter(&bits);
+        assert!(!filter.as_filter().get_bit(31));
+    }
+
     #[test]
     fn or_ops() {
         let mut filter1 = Filter::<4>::new();
@@ -163,4 +678,150 @@ mod tests {
         let filter3 = filter1 | filter2;
         assert_eq!(filter3.count_set_bits(), 2);
     }
+
+    #[test]
+    fn bulk_ops() {
+        // 13 bytes is not a multiple of the word size, so the last word is partially used
+        let mut filter1 = Filter::<13>::new();
+        let mut filter2 = Filter::<13>::new();
+        for bit in [0, 63, 64, 100] {
+            filter1.set_bit(bit, true);
+        }
+        for bit in [63, 100, 103] {
+            filter2.set_bit(bit, true);
+        }
+        filter1.set_bit(104, true);
+        assert!(!filter1.get_bit(104));
+        assert_eq!(filter1.count_common_bits(&filter2), 2);
+        assert!(filter1.contains_all(&[0, 63, 100]));
+        assert!(!filter1.contains_all(&[0, 103]));
+        assert!(!filter1.contains_all(&[1000]));
+        assert!(filter1.contains_all(&[]));
+
+        let mut and = filter1.clone();
+        and.bitand_assign_ref(&filter2);
+        assert_eq!(and.iter_set_bits().collect::<Vec<_>>(), vec![63, 100]);
+
+        let mut xor = filter1.clone();
+        xor.bitxor_assign_ref(&filter2);
+        assert_eq!(xor.iter_set_bits().collect::<Vec<_>>(), vec![0, 64, 103]);
+
+        let and_not = filter1.and_not(&filter2);
+        assert_eq!(and_not.iter_set_bits().collect::<Vec<_>>(), vec![0, 64]);
+
+        let mut or = filter1.clone();
+        or.bitor_assign_ref(&filter2);
+        assert_eq!(or.count_set_bits(), 5);
+
+        // The byte representation is unchanged by the word storage
+        let bytes: Vec<u8> = (&or).into();
+        assert_eq!(bytes.len(), 13);
+        assert_eq!(bytes[0], 1);
+        assert_eq!(bytes[12], 0b1001_0000);
+        assert_eq!(Filter::<13>::from(bytes.as_slice()), or);
+    }
+
+    #[test]
+    fn algebra() {
+        let mut filter1 = Filter::<4>::new();
+        let mut filter2 = Filter::<4>::new();
+        for bit in [1, 2, 3] {
+            filter1.set_bit(bit, true);
+        }
+        for bit in [2, 3, 4, 5] {
+            filter2.set_bit(bit, true);
+        }
+        assert_eq!((filter1.clone() & filter2.clone()).iter_set_bits().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!((filter1.clone() ^ filter2.clone()).iter_set_bits().collect::<Vec<_>>(), vec![1, 4, 5]);
+        assert!(!filter1.is_subset_of(&filter2));
+        assert!((filter1.clone() & filter2.clone()).is_subset_of(&filter2));
+        assert!(Filter::<4>::new().is_subset_of(&filter1));
+        assert_eq!(filter1.jaccard_similarity(&filter2), 0.4);
+        assert_eq!(filter1.jaccard_similarity(&filter1), 1.0);
+        assert_eq!(Filter::<4>::new().jaccard_similarity(&Filter::new()), 1.0);
+    }
+
+    #[test]
+    fn estimations() {
+        // A store with 3 hashes per word that added 1000 distinct words
+        let mut filter = Filter::<1000>::new();
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..3000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            filter.set_bit(state as usize % 8000, true);
+        }
+        let estimation = filter.estimated_len(3);
+        assert!((950.0..1050.0).contains(&estimation), "{estimation}");
+        let load = filter.load();
+        assert_eq!(filter.false_positive_rate(3), load * load * load);
+
+        assert_eq!(Filter::<4>::new().estimated_len(3), 0.0);
+        assert_eq!(Filter::<4>::new().false_positive_rate(3), 0.0);
+        let mut full = Filter::<4>::new();
+        (0..32).for_each(|bit| full.set_bit(bit, true));
+        assert_eq!(full.estimated_len(3), f64::INFINITY);
+        assert_eq!(full.false_positive_rate(3), 1.0);
+    }
+
+    #[test]
+    fn encodings() {
+        let mut sparse = Filter::<20000>::new();
+        for bit in [0, 7, 8, 4000, 65535, 65536, 159_999] {
+            sparse.set_bit(bit, true);
+        }
+        let mut dense = Filter::<20000>::new();
+        for bit in (0..160_000).step_by(3) {
+            dense.set_bit(bit, true);
+        }
+
+        for filter in [Filter::new(), sparse.clone(), dense] {
+            for encoding in FilterEncoding::all() {
+                let data = filter.encode(encoding);
+                assert_eq!(Filter::decode(&data, encoding), Ok(filter.clone()), "{encoding:?}");
+            }
+        }
+
+        assert!(sparse.encode(FilterEncoding::RunLength).len() < 100);
+        assert!(sparse.encode(FilterEncoding::Bitmap).len() < 100);
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        assert_eq!(Filter::<4>::decode(&[0; 3], FilterEncoding::Raw), Err(FilterDecodingError::InvalidLength { expected: 4, got: 3 }));
+        assert_eq!(Filter::<4>::decode(&[0, 5, 1], FilterEncoding::RunLength), Err(FilterDecodingError::Truncated));
+        assert_eq!(Filter::<4>::decode(&[3, 0], FilterEncoding::RunLength), Err(FilterDecodingError::InvalidLength { expected: 4, got: 3 }));
+        let mut overflowing = Vec::new();
+        push_varint(&mut overflowing, 1);
+        push_varint(&mut overflowing, 0);
+        push_varint(&mut overflowing, usize::MAX);
+        push_varint(&mut overflowing, 1);
+        overflowing.push(1);
+        assert_eq!(Filter::<4>::decode(&overflowing, FilterEncoding::RunLength), Err(FilterDecodingError::Malformed));
+        let mut overflowing = Vec::new();
+        push_varint(&mut overflowing, usize::MAX);
+        push_varint(&mut overflowing, 1);
+        overflowing.push(1);
+        assert_eq!(Filter::<4>::decode(&overflowing, FilterEncoding::RunLength), Err(FilterDecodingError::Malformed));
+        assert_eq!(Filter::<4>::decode(&[1, 0, 0, 1, 32, 0], FilterEncoding::Bitmap), Err(FilterDecodingError::Malformed));
+        assert_eq!(Filter::<4>::decode(&[2], FilterEncoding::Bitmap), Err(FilterDecodingError::Malformed));
+    }
+
+    #[test]
+    fn diff() {
+        let mut filter1 = Filter::<4>::new();
+        filter1.set_bit(3, true);
+        filter1.set_bit(17, true);
+        let mut filter2 = Filter::<4>::new();
+        filter2.set_bit(17, true);
+        filter2.set_bit(30, true);
+        let diff = filter1.diff(&filter2);
+        assert_eq!(diff, vec![3, 30]);
+
+        for idx in diff {
+            filter1.flip_bit(idx);
+        }
+        assert_eq!(filter1, filter2);
+    }
 }
@@ -14,7 +14,7 @@ pub enum BehaviorToHandlerEvent<const N: usize, S: Store<N>> {
         query: Arc<S::Query>,
         routes_sender: Sender<Vec<Route>>,
         result_sender: OngoingSearchFollower<N, S>,
-        over_notifier: OneshotSender<()>,
+        over_notifier: OneshotSender<usize>,
     },
     /// Asks the handler to leech filters
     LeechFilters,
@@ -22,6 +22,8 @@ pub enum BehaviorToHandlerEvent<const N: usize, S: Store<N>> {
     StopLeeching,
     /// Asks the handler to stop seeding
     StopSeeding,
+    /// Asks the handler to send a [DisconnectPacket] and close the connection
+    Disconnect(DisconnectPacket),
 }
 
 impl<const N: usize, S: Store<N>> std::fmt::Debug for BehaviorToHandlerEvent<N, S> {
@@ -32,6 +34,7 @@ impl<const N: usize, S: Store<N>> std::fmt::Debug for BehaviorToHandlerEvent<N,
             BehaviorToHandlerEvent::LeechFilters => write!(f, "LeechFilters"),
             BehaviorToHandlerEvent::StopLeeching => write!(f, "StopLeeching"),
             BehaviorToHandlerEvent::StopSeeding => write!(f, "StopSeeding"),
+            BehaviorToHandlerEvent::Disconnect(packet) => write!(f, "Disconnect({packet:?})"),
         }
     }
 }
@@ -57,11 +60,19 @@ pub struct KamilataHandler<const N: usize, S: Store<N>> {
     ///     2: filter leecher
     tasks: HashMap<u32, HandlerTask>,
     /// Tasks waiting to be inserted into the `tasks` map, because their outbound substream is still opening.
-    pending_tasks: Vec<(Option<(u32, bool)>, PendingHandlerTask<Box<dyn Any + Send>>)>,
+    pending_tasks: Vec<PendingTaskInfo>,
+    /// Requests other than handshakes and disconnections wait for the handshake to succeed.
+    handshake: watch::Sender<HandshakeState>,
 }
 
 impl<const N: usize, S: Store<N>> KamilataHandler<N, S> {
-    pub(crate) fn new(our_peer_id: PeerId, remote_peer_id: PeerId, db: Arc<Db<N, S>>, config: Arc<KamilataConfig>) -> Self {
+    /// Creates a handler for a new connection.
+    /// The dialer starts by sending a handshake so that incompatible peers are detected right away.
+    pub(crate) fn new(our_peer_id: PeerId, remote_peer_id: PeerId, endpoint: Endpoint, db: Arc<Db<N, S>>, config: Arc<KamilataConfig>) -> Self {
+        let mut pending_tasks = Vec::new();
+        if endpoint == Endpoint::Dialer {
+            pending_tasks.push((None, pending_handshake(Arc::clone(&db), our_peer_id, remote_peer_id)));
+        }
         KamilataHandler {
             our_peer_id,
             remote_peer_id,
@@ -70,19 +81,53 @@ impl<const N: usize, S: Store<N>> KamilataHandler<N, S> {
             rt_handle: tokio::runtime::Handle::current(),
             task_counter: Counter::new(3),
             tasks: HashMap::new(),
-            pending_tasks: Vec::new(),
+            pending_tasks,
+            handshake: watch::channel(HandshakeState::Pending).0,
+        }
+    }
+
+    /// Returns the next pending task whose substream can be opened.
+    /// Until the handshake succeeded, only the handshake and disconnections are.
+    fn next_pending_task(&mut self) -> Option<PendingTaskInfo> {
+        match *self.handshake.borrow() {
+            HandshakeState::Succeeded => self.pending_tasks.pop(),
+            _ => {
+                let position = self.pending_tasks.iter().rposition(|(_, pending_task)| matches!(pending_task.name, "handshake" | "send_disconnect"))?;
+                Some(self.pending_tasks.remove(position))
+            },
         }
     }
+
+    /// Releases the leecher spot of the remote peer once we stopped seeding to it.
+    fn on_leecher_removed(&self) {
+        let db = Arc::clone(&self.db);
+        let remote_peer_id = self.remote_peer_id;
+        tokio::spawn(async move {
+            db.remove_leecher(&remote_peer_id).await;
+            db.behaviour_controller().emit_event(KamilataEvent::LeecherRemoved { peer_id: remote_peer_id }).await;
+        });
+    }
+
+    /// Releases the seeder spot of the remote peer once we stopped leeching from it.
+    fn on_seeder_removed(&self) {
+        let db = Arc::clone(&self.db);
+        let remote_peer_id = self.remote_peer_id;
+        tokio::spawn(async move {
+            db.remove_seeder(&remote_peer_id).await;
+            db.behaviour_controller().emit_event(KamilataEvent::SeederRemoved { peer_id: remote_peer_id }).await;
+        });
+    }
 }
 
 impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
     type FromBehaviour = BehaviorToHandlerEvent<N, S>;
     type ToBehaviour = HandlerToBehaviorEvent;
+    #[allow(deprecated)]
     type Error = ioError;
     type InboundProtocol = Either<ArcConfig, DeniedUpgrade>;
     type OutboundProtocol = ArcConfig;
     type InboundOpenInfo = ();
-    type OutboundOpenInfo = (Option<(u32, bool)>, PendingHandlerTask<Box<dyn Any + Send>>);
+    type OutboundOpenInfo = PendingTaskInfo;
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
         SubstreamProtocol::new(ArcConfig::from(&self.config), ()).map_upgrade(Either::Left)
@@ -111,22 +156,18 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
             BehaviorToHandlerEvent::StopLeeching => {
                 self.pending_tasks.retain(|(_, pending_task)| pending_task.name != "leech_filters");
                 if self.tasks.remove(&2).is_some() {
-                    let behaviour_controller = self.db.behaviour_controller().clone();
-                    let remote_peer_id = self.remote_peer_id;
-                    tokio::spawn(async move {
-                        behaviour_controller.emit_event(KamilataEvent::SeederRemoved { peer_id: remote_peer_id }).await;
-                    });
+                    self.on_seeder_removed();
                 }
             },
             BehaviorToHandlerEvent::StopSeeding => {
                 if self.tasks.remove(&1).is_some() {
-                    let behaviour_controller = self.db.behaviour_controller().clone();
-                    let remote_peer_id = self.remote_peer_id;
-                    tokio::spawn(async move {
-                        behaviour_controller.emit_event(KamilataEvent::LeecherRemoved { peer_id: remote_peer_id }).await;
-                    });
+                    self.on_leecher_removed();
                 }
             },
+            BehaviorToHandlerEvent::Disconnect(packet) => {
+                let pending_task = pending_send_disconnect(packet, Arc::clone(&self.db), self.our_peer_id, self.remote_peer_id);
+                self.pending_tasks.push((None, pending_task));
+            },
         };
     }
 
@@ -134,7 +175,6 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
         KeepAlive::Yes
     }
 
-    #[warn(implied_bounds_entailment)]
     fn on_connection_event(
         &mut self,
         event: ConnectionEvent<
@@ -153,7 +193,7 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
                 };
         
                 // TODO: prevent DoS
-                let fut = handle_request(substream, Arc::clone(&self.db), self.our_peer_id, self.remote_peer_id).boxed();
+                let fut = handle_request(substream, Arc::clone(&self.db), self.handshake.subscribe(), self.our_peer_id, self.remote_peer_id).boxed();
                 self.tasks.insert(self.task_counter.next(), HandlerTask { fut, name: "handle_request" });
             },
             // Once an outbound is fully negotiated, the pending task which requested the establishment of the channel is now ready to be executed.
@@ -177,6 +217,7 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
         }
     }
 
+    #[allow(deprecated)]
     fn poll(
         &mut self,
         cx: &mut Context<'_>,
@@ -202,20 +243,8 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
                     self.tasks.remove(&tid);
                     
                     match tid {
-                        1 => {
-                            let behaviour_controller = self.db.behaviour_controller().clone();
-                            let remote_peer_id = self.remote_peer_id;
-                            tokio::spawn(async move {
-                                behaviour_controller.emit_event(KamilataEvent::LeecherRemoved { peer_id: remote_peer_id }).await;
-                            });
-                        }
-                        2 => {
-                            let behaviour_controller = self.db.behaviour_controller().clone();
-                            let remote_peer_id = self.remote_peer_id;
-                            tokio::spawn(async move {
-                                behaviour_controller.emit_event(KamilataEvent::SeederRemoved { peer_id: remote_peer_id }).await;
-                            });
-                        }
+                        1 => self.on_leecher_removed(),
+                        2 => self.on_seeder_removed(),
                         _ => ()
                     }
 
@@ -238,12 +267,17 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
                                 trace!("{} New pending task: {}", self.our_peer_id, pending_task.name);
                                 self.pending_tasks.push((tid, pending_task));
                             },
+                            HandlerTaskOutput::HandshakeFinished { compatible } => {
+                                self.handshake.send_replace(if compatible { HandshakeState::Succeeded } else { HandshakeState::Failed });
+                                if !compatible && !self.pending_tasks.is_empty() {
+                                    debug!("{} Discarding {} pending tasks as the handshake with {} failed", self.our_peer_id, self.pending_tasks.len(), self.remote_peer_id);
+                                    self.pending_tasks.retain(|(_, pending_task)| pending_task.name == "send_disconnect");
+                                }
+                            },
                             HandlerTaskOutput::Disconnect(disconnect_packet) => {
-                                debug!("{} Disconnected peer {}", self.our_peer_id, self.remote_peer_id);
-                                // TODO: send packet
-                                return Poll::Ready(ConnectionHandlerEvent::Close(
-                                    ioError::new(std::io::ErrorKind::Other, disconnect_packet.reason), // TODO error handling
-                                ));
+                                debug!("{} Disconnecting peer {}", self.our_peer_id, self.remote_peer_id);
+                                let pending_task = pending_send_disconnect(disconnect_packet, Arc::clone(&self.db), self.our_peer_id, self.remote_peer_id);
+                                self.pending_tasks.push((None, pending_task));
                             },
                             HandlerTaskOutput::None | HandlerTaskOutput::Many(_) => unreachable!(),
                         }
@@ -253,7 +287,7 @@ impl<const N: usize, S: Store<N>> ConnectionHandler for KamilataHandler<N, S> {
             }
         }   
 
-        if let Some((tid, pending_task)) = self.pending_tasks.pop() {
+        if let Some((tid, pending_task)) = self.next_pending_task() {
             return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
                 protocol: SubstreamProtocol::new(ArcConfig::from(&self.config), (tid, pending_task)),
             })
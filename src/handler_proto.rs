@@ -46,13 +46,13 @@ where
                 .err_into()
                 .with::<_, _, fn(_) -> _, _>(|response: ResponsePacket| {
                     let stream = response.into_stream(&ProtocolSettings::default()).map_err(|e| {
-                        ioError::new(std::io::ErrorKind::Other, e.to_string()) // TODO: error handling
+                        ioError::other(e.to_string()) // TODO: error handling
                     });
                     future::ready(stream)
                 })
                 .and_then::<_, fn(_) -> _>(|bytes: BytesMut| {
                     let request = RequestPacket::from_raw_bytes(&bytes, &ProtocolSettings::default()).map_err(|e| {
-                        ioError::new(std::io::ErrorKind::Other, e.to_string()) // TODO: error handling
+                        ioError::other(e.to_string()) // TODO: error handling
                     });
                     future::ready(request)
                 }),
@@ -79,13 +79,13 @@ where
                 .err_into()
                 .with::<_, _, fn(_) -> _, _>(|request: RequestPacket| {
                     let stream = request.into_stream(&ProtocolSettings::default()).map_err(|e| {
-                        ioError::new(std::io::ErrorKind::Other, e.to_string()) // TODO error handling
+                        ioError::other(e.to_string()) // TODO error handling
                     });
                     future::ready(stream)
                 })
                 .and_then::<_, fn(_) -> _>(|bytes: BytesMut| {
                     let response = ResponsePacket::from_raw_bytes(&bytes, &ProtocolSettings::default()).map_err(|e| {
-                        ioError::new(std::io::ErrorKind::Other, e.to_string()) // TODO error handling
+                        ioError::other(e.to_string()) // TODO error handling
                     });
                     future::ready(response)
                 }),
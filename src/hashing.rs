@@ -0,0 +1,108 @@
+//! Standard ways of hashing words into filter bit indices.
+//!
+//! Nodes must hash words the same way to understand each other's filters.
+//! The [identity](WordHasher::identity) of the hasher is therefore sent to seeders, which refuse leechers hashing differently.
+
+/// Seed of the default hasher, shared by all nodes of the public network.
+///
+/// Private networks can use another seed so that their filters are meaningless to other nodes.
+pub const DEFAULT_NETWORK_SEED: u64 = 0;
+
+/// Describes how a [WordHasher] maps words to bits.
+/// Two hashers with the same identity must produce the same indices for the same words.
+#[derive(protocol::Protocol, Debug, Clone, PartialEq, Eq)]
+pub struct HasherIdentity {
+    /// Name of the hashing algorithm
+    pub scheme: String,
+    /// Number of indices returned for each word
+    pub hash_count: u8,
+    pub seed: u64,
+}
+
+/// Maps words to bit indices in filters.
+pub trait WordHasher: Send + Sync {
+    /// Returns the indices of the bits representing `word` in a filter of `bit_len` bits.
+    ///
+    /// Must return at least one value, and only values lower than `bit_len`.
+    fn hash_word(&self, word: &str, bit_len: usize) -> Vec<usize>;
+
+    /// Returns the identity advertised to peers.
+    fn identity(&self) -> HasherIdentity;
+}
+
+/// Hashes words with a seeded FNV-1a and derives `hash_count` indices by double hashing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoubleHasher {
+    hash_count: u8,
+    seed: u64,
+}
+
+impl DoubleHasher {
+    /// The hasher used by [Store::hash_word](crate::store::Store::hash_word) unless the store overrides it.
+    pub const DEFAULT: DoubleHasher = DoubleHasher::new(3, DEFAULT_NETWORK_SEED);
+
+    /// Creates a hasher returning `hash_count` indices per word.
+    ///
+    /// Panics if `hash_count` is zero.
+    pub const fn new(hash_count: u8, seed: u64) -> DoubleHasher {
+        assert!(hash_count > 0, "hash_count must be at least 1");
+        DoubleHasher { hash_count, seed }
+    }
+
+    pub const fn hash_count(&self) -> u8 {
+        self.hash_count
+    }
+
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for DoubleHasher {
+    fn default() -> Self {
+        DoubleHasher::DEFAULT
+    }
+}
+
+impl WordHasher for DoubleHasher {
+    fn hash_word(&self, word: &str, bit_len: usize) -> Vec<usize> {
+        let mut hash: u64 = 0xcbf29ce484222325 ^ self.seed;
+        for byte in word.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        // Indices may coincide for some words since filter sizes are rarely prime, which only sets fewer bits for these words
+        let (h1, h2) = (hash >> 32, (hash & 0xffffffff) | 1);
+        (0..self.hash_count as u64).map(|i| (h1.wrapping_add(i * h2) % bit_len as u64) as usize).collect()
+    }
+
+    fn identity(&self) -> HasherIdentity {
+        HasherIdentity {
+            scheme: String::from("fnv1a-double"),
+            hash_count: self.hash_count,
+            seed: self.seed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_hasher() {
+        let hasher = DoubleHasher::DEFAULT;
+        let hashes = hasher.hash_word("kamilata", 1000);
+        assert_eq!(hashes.len(), 3);
+        assert!(hashes.iter().all(|h| *h < 1000));
+        assert_eq!(hashes, hasher.hash_word("kamilata", 1000));
+        assert_ne!(hashes, hasher.hash_word("kamilato", 1000));
+
+        let hasher = DoubleHasher::new(5, 42);
+        assert_eq!(hasher.hash_word("kamilata", 1024).len(), 5);
+        assert_ne!(hasher.hash_word("kamilata", 1024)[0], DoubleHasher::new(5, 43).hash_word("kamilata", 1024)[0]);
+        assert_ne!(hasher.identity(), DoubleHasher::new(5, 43).identity());
+        assert_ne!(hasher.identity(), DoubleHasher::new(4, 42).identity());
+        assert_eq!(hasher.identity(), DoubleHasher::new(5, 42).identity());
+    }
+}
@@ -1,13 +1,22 @@
+// The `Protocol` derive macro generates impls inside anonymous constants
+#![allow(non_local_definitions)]
+
 pub mod behaviour;
+pub mod capabilities;
 pub mod config;
 pub mod control;
 pub(crate) mod counter;
 pub mod db;
 pub mod store;
 pub mod filters;
+pub mod hashing;
 pub mod handler;
 pub(crate) mod handler_proto;
 pub(crate) mod packets;
 pub mod prelude;
 pub(crate) mod tasks;
 pub mod queries;
+pub mod tokenizer;
+pub mod topology;
+#[cfg(feature = "memory-store")]
+pub mod memory_store;
@@ -0,0 +1,267 @@
+//! A reference [Store] implementation keeping documents in memory.
+//! 
+//! It is a good starting point to build a working node, and can be replaced by a custom store later on.
+
+use crate::prelude::*;
+use async_trait::async_trait;
+use std::collections::BTreeSet;
+
+/// A document that can be stored in an [InMemoryStore].
+pub trait Document: SearchResult + Clone + Send + Sync + 'static {
+    /// Returns the text under which the document should be found.
+    /// It is split into words by the store's [tokenizer](Store::TOKENIZER).
+    fn text(&self) -> String;
+}
+
+/// Returns the distinct words of a document.
+fn words<const N: usize, D: Document>(document: &D) -> BTreeSet<String> {
+    InMemoryStore::<N, D>::TOKENIZER.tokenize(&document.text()).into_iter().collect()
+}
+
+struct InMemoryStoreInner<const N: usize, D: Document> {
+    /// Documents by internal id
+    documents: HashMap<usize, D>,
+    /// Internal id of the next inserted document
+    next_id: usize,
+    ids: HashMap<D::Cid, usize>,
+    /// Internal ids of the documents containing each word
+    index: HashMap<String, BTreeSet<usize>>,
+    filter: CountingFilter<N>,
+}
+
+/// A [Store] keeping documents in memory, with an inverted index from words to documents.
+/// 
+/// The filter is updated incrementally as documents are inserted and removed.
+/// Searches are made with [BooleanQuery] and results are loaded lazily, best scores first.
+pub struct InMemoryStore<const N: usize, D: Document> {
+    inner: Arc<RwLock<InMemoryStoreInner<N, D>>>,
+}
+
+impl<const N: usize, D: Document> Default for InMemoryStore<N, D> {
+    fn default() -> Self {
+        InMemoryStore::new()
+    }
+}
+
+impl<const N: usize, D: Document> InMemoryStore<N, D> {
+    pub fn new() -> InMemoryStore<N, D> {
+        InMemoryStore {
+            inner: Arc::new(RwLock::new(InMemoryStoreInner {
+                documents: HashMap::new(),
+                next_id: 0,
+                ids: HashMap::new(),
+                index: HashMap::new(),
+                filter: CountingFilter::new(),
+            })),
+        }
+    }
+
+    /// Inserts a document, replacing the one with the same [cid](SearchResult::cid) if any.
+    pub async fn insert_document(&self, document: D) {
+        self.insert_documents(std::iter::once(document)).await
+    }
+
+    /// Inserts documents, replacing those with the same [cid](SearchResult::cid) if any.
+    pub async fn insert_documents(&self, documents: impl IntoIterator<Item = D>) {
+        let mut inner = self.inner.write().await;
+        for document in documents {
+            if let Some(id) = inner.ids.get(&document.cid()).copied() {
+                inner.remove(id);
+            }
+            let id = inner.next_id;
+            inner.next_id += 1;
+            for word in words::<N, D>(&document) {
+                inner.filter.add_word::<Self>(&word);
+                inner.index.entry(word).or_default().insert(id);
+            }
+            inner.ids.insert(document.cid(), id);
+            inner.documents.insert(id, document);
+        }
+    }
+
+    /// Removes a document and returns it.
+    pub async fn remove_document(&self, cid: &D::Cid) -> Option<D> {
+        let mut inner = self.inner.write().await;
+        let id = inner.ids.get(cid).copied()?;
+        inner.remove(id)
+    }
+
+    /// Returns a copy of a document.
+    pub async fn get_document(&self, cid: &D::Cid) -> Option<D> {
+        let inner = self.inner.read().await;
+        let id = inner.ids.get(cid)?;
+        inner.documents.get(id).cloned()
+    }
+
+    /// Returns the number of documents.
+    pub async fn len(&self) -> usize {
+        self.inner.read().await.ids.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.inner.read().await.ids.is_empty()
+    }
+}
+
+impl<const N: usize, D: Document> InMemoryStoreInner<N, D> {
+    fn remove(&mut self, id: usize) -> Option<D> {
+        let document = self.documents.remove(&id)?;
+        self.ids.remove(&document.cid());
+        for word in words::<N, D>(&document) {
+            self.filter.remove_word::<InMemoryStore<N, D>>(&word);
+            if let Some(ids) = self.index.get_mut(&word) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.index.remove(&word);
+                }
+            }
+        }
+        Some(document)
+    }
+}
+
+#[async_trait]
+impl<const N: usize, D: Document> Store<N> for InMemoryStore<N, D> {
+    type Result = D;
+    type Query = BooleanQuery<Self>;
+
+    async fn get_filter(&self) -> Filter<N> {
+        self.inner.read().await.filter.as_filter().clone()
+    }
+
+    fn search(&self, query: Arc<Self::Query>) -> ResultStreamBuilderFut<D> {
+        let inner = Arc::clone(&self.inner);
+        Box::pin(async move {
+            // Only documents containing at least one positive term can match
+            let guard = inner.read().await;
+            let mut candidates = BTreeSet::new();
+            for word in query.root().positive_terms() {
+                if let Some(ids) = guard.index.get(word) {
+                    candidates.extend(ids.iter().copied());
+                }
+            }
+            let mut matches = candidates.into_iter().filter_map(|id| {
+                let score = query.score_with(|word| guard.index.get(word).is_some_and(|ids| ids.contains(&id)));
+                (score > 0).then_some((id, score))
+            }).collect::<Vec<_>>();
+            drop(guard);
+            matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+            // Documents are only copied when the stream is polled
+            let stream = futures::stream::iter(matches).filter_map(move |(id, _)| {
+                let inner = Arc::clone(&inner);
+                async move { inner.read().await.documents.get(&id).cloned() }
+            });
+            let stream: ResultStream<D> = Box::pin(stream);
+            stream
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Doc {
+        id: u32,
+        text: &'static str,
+    }
+
+    impl SearchResult for Doc {
+        type Cid = u32;
+        type ParsingError = std::array::TryFromSliceError;
+
+        fn cid(&self) -> u32 {
+            self.id
+        }
+
+        /// Only the id is sent, as the text is static.
+        fn into_bytes(self) -> Vec<u8> {
+            self.id.to_be_bytes().to_vec()
+        }
+
+        fn from_bytes(bytes: &[u8]) -> Result<Self, Self::ParsingError> {
+            Ok(Doc { id: u32::from_be_bytes(bytes.try_into()?), text: "" })
+        }
+    }
+
+    impl Document for Doc {
+        fn text(&self) -> String {
+            self.text.to_string()
+        }
+    }
+
+    type TestStore = InMemoryStore<128, Doc>;
+
+    async fn search(store: &TestStore, query: &str) -> Vec<u32> {
+        let query = Arc::new(BooleanQuery::parse(query).unwrap());
+        store.search(query).await.map(|doc| doc.id).collect().await
+    }
+
+    #[tokio::test]
+    async fn insert_and_search() {
+        let store = TestStore::new();
+        store.insert_documents([
+            Doc { id: 0, text: "rust p2p search engine" },
+            Doc { id: 1, text: "java search engine" },
+            Doc { id: 2, text: "rust libp2p" },
+        ]).await;
+        assert_eq!(store.len().await, 3);
+
+        let filter = store.get_filter().await;
+        assert!(filter.get_word::<TestStore>("libp2p"));
+        assert!(BooleanQuery::<TestStore>::parse("rust AND p2p").unwrap().match_score(&filter) > 0);
+
+        assert_eq!(search(&store, "rust").await, vec![0, 2]);
+        assert_eq!(search(&store, "rust AND (p2p OR libp2p)").await, vec![0, 2]);
+        assert_eq!(search(&store, "search -java").await, vec![0]);
+        assert_eq!(search(&store, "java OR rust^2").await, vec![0, 2, 1]);
+        assert!(search(&store, "python").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn replace_and_remove() {
+        let store = TestStore::new();
+        store.insert_document(Doc { id: 0, text: "rust" }).await;
+        store.insert_document(Doc { id: 0, text: "java" }).await;
+        assert_eq!(store.len().await, 1);
+        assert!(search(&store, "rust").await.is_empty());
+        assert_eq!(search(&store, "java").await, vec![0]);
+
+        assert_eq!(store.remove_document(&0).await, Some(Doc { id: 0, text: "java" }));
+        assert!(store.is_empty().await);
+        assert!(search(&store, "java").await.is_empty());
+        assert!(store.get_filter().await.is_empty());
+
+        // Words shared with remaining documents stay in the filter
+        store.insert_documents([Doc { id: 1, text: "rust p2p" }, Doc { id: 2, text: "rust java" }]).await;
+        store.remove_document(&2).await;
+        let filter = store.get_filter().await;
+        assert!(filter.get_word::<TestStore>("rust"));
+        assert!(filter.get_word::<TestStore>("p2p"));
+        let mut expected = Filter::<128>::new();
+        expected.add_word::<TestStore>("rust");
+        expected.add_word::<TestStore>("p2p");
+        assert_eq!(filter, expected);
+
+        // Removed documents don't leave anything behind
+        assert_eq!(store.inner.read().await.documents.len(), 1);
+    }
+
+    #[test]
+    fn doc_bytes() {
+        let doc = Doc::from_bytes(&Doc { id: 3, text: "rust" }.into_bytes()).unwrap();
+        assert_eq!(doc.cid(), 3);
+        assert!(Doc::from_bytes(&[0, 3]).is_err());
+    }
+
+    #[tokio::test]
+    async fn normalization() {
+        let store = TestStore::new();
+        store.insert_document(Doc { id: 0, text: "Crème brûlée, Café" }).await;
+        assert_eq!(search(&store, "CAFE").await, vec![0]);
+        assert_eq!(search(&store, "creme-brulee").await, vec![0]);
+        assert!(store.get_filter().await.get_word::<TestStore>("cafe"));
+    }
+}
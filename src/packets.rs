@@ -1,5 +1,6 @@
 use protocol_derive::Protocol;
-use crate::config::MinTargetMax;
+use crate::{capabilities::Capabilities, config::MinTargetMax, filters::{Filter, FilterEncoding, FilterDecodingError}, hashing::{DoubleHasher, HasherIdentity, WordHasher}, topology::RejectionReason};
+use std::time::Duration;
 
 #[derive(Clone)]
 #[repr(transparent)]
@@ -54,9 +55,7 @@ pub trait HackTraitVecPeerId {
 }
 impl HackTraitVecPeerId for Vec<PeerId> {
     fn to_libp2p_peer_ids(self) -> Vec<libp2p::PeerId> {
-        unsafe {
-            std::mem::transmute_copy(&self)
-        }
+        self.into_iter().map(|peer_id| peer_id.0).collect()
     }
 }
 
@@ -67,8 +66,13 @@ pub enum RequestPacket {
     GetFilters(GetFiltersPacket),
     /// Asks to apply our query on its documents and return results in the [ResponsePacket::ReturnResults] packet.
     Search(SearchPacket),
-
+    /// Informs the peer we are closing the connection and whether it may come back.
     Disconnect(DisconnectPacket),
+    /// Sent on the substream of a [RequestPacket::Search] to tell the peer we no longer want results.
+    CancelSearch,
+    /// Sent by the dialer as soon as a connection is established.
+    /// The peer answers with [ResponsePacket::Handshake].
+    Handshake(Capabilities),
 }
 
 #[derive(Protocol, Debug, Clone)]
@@ -79,6 +83,10 @@ pub struct GetFiltersPacket {
     pub interval: MinTargetMax,
     /// Peers we don't want to hear from
     pub blocked_peers: Vec<PeerId>,
+    /// Filter encodings we are able to decode
+    pub encodings: Vec<FilterEncoding>,
+    /// How we hash words, which must match the seeder's for our filters to be meaningful
+    pub hasher: HasherIdentity,
 }
 
 impl Default for GetFiltersPacket {
@@ -91,10 +99,13 @@ impl Default for GetFiltersPacket {
                 max: 60_000,
             },
             blocked_peers: Vec::new(),
+            encodings: FilterEncoding::all(),
+            hasher: DoubleHasher::DEFAULT.identity(),
         }
     }
 }
 
+#[allow(dead_code)]
 /// For a filter to match a query, it must have at least `match_count` bits set to 1 at the positions specified by hashed `words`.
 #[derive(Protocol, Debug, Clone)]
 pub struct Query {
@@ -124,12 +135,84 @@ pub enum ResponsePacket {
     SearchOver,
 
     Disconnect(DisconnectPacket),
+    /// Sent instead of [ResponsePacket::UpdateFilters] once the peer knows our filters.
+    /// If the peer fails to apply the deltas, it sends [RequestPacket::GetFilters] again on the same channel to get a full update.
+    UpdateFiltersDelta(UpdateFiltersDeltaPacket),
+    /// Sent instead of [ResponsePacket::UpdateFilters] when our filters didn't change since the last update.
+    FiltersUnchanged,
+    /// Response to a [RequestPacket::GetFilters] packet we refuse.
+    /// The channel is closed right after.
+    Rejected(RejectionPacket),
+    /// Response to a [RequestPacket::Handshake] packet.
+    Handshake(Capabilities),
 }
 
 #[derive(Protocol, Debug, Clone)]
 pub struct UpdateFiltersPacket {
     /// The filters ordered from distance 0 to the furthest at a distance of [RefreshPacket::range].
-    pub filters: Vec<Vec<u8>>,
+    pub filters: Vec<EncodedFilter>,
+    /// The interval negotiated from [GetFiltersPacket::interval], that further updates respect.
+    pub interval: MinTargetMax,
+}
+
+#[derive(Protocol, Debug, Clone)]
+pub struct EncodedFilter {
+    /// Encoding of the filter, one of [GetFiltersPacket::encodings].
+    pub encoding: FilterEncoding,
+    pub data: Vec<u8>,
+}
+
+impl EncodedFilter {
+    /// Encodes a filter with the most compact of the allowed encodings.
+    /// [FilterEncoding::Raw] is used if no encoding is allowed.
+    pub fn new<const N: usize>(filter: &Filter<N>, encodings: &[FilterEncoding]) -> EncodedFilter {
+        encodings
+            .iter()
+            .map(|encoding| EncodedFilter { encoding: *encoding, data: filter.encode(*encoding) })
+            .min_by_key(|encoded| encoded.data.len())
+            .unwrap_or_else(|| EncodedFilter { encoding: FilterEncoding::Raw, data: filter.encode(FilterEncoding::Raw) })
+    }
+
+    pub fn decode<const N: usize>(&self) -> Result<Filter<N>, FilterDecodingError> {
+        Filter::decode(&self.data, self.encoding)
+    }
+}
+
+#[derive(Protocol, Debug, Clone)]
+pub struct UpdateFiltersDeltaPacket {
+    /// Changes to apply to each filter, ordered the same way as in [UpdateFiltersPacket].
+    /// Filters beyond the length of this list must be dropped.
+    pub deltas: Vec<FilterDelta>,
+}
+
+#[derive(Protocol, Debug, Clone)]
+pub enum FilterDelta {
+    /// The whole filter, sent when it is new or when it changed too much.
+    Full(EncodedFilter),
+    /// Indices of the bits that flipped since the previous update.
+    Flipped {
+        bits: Vec<u32>,
+        /// Number of bits set to 1 in the resulting filter, used to detect desynchronization.
+        set_bits: u32,
+    },
+}
+
+impl FilterDelta {
+    /// Computes the delta between the filter previously sent (if any) and the new one.
+    pub fn new<const N: usize>(previous: Option<&Filter<N>>, filter: &Filter<N>, encodings: &[FilterEncoding]) -> FilterDelta {
+        let Some(previous) = previous else {
+            return FilterDelta::Full(EncodedFilter::new(filter, encodings));
+        };
+        let bits = previous.diff(filter);
+        // Each index takes 4 bytes while the whole filter takes at most N
+        if bits.len() * 4 >= N {
+            return FilterDelta::Full(EncodedFilter::new(filter, encodings));
+        }
+        FilterDelta::Flipped {
+            bits: bits.into_iter().map(|b| b as u32).collect(),
+            set_bits: filter.count_set_bits() as u32,
+        }
+    }
 }
 
 #[derive(Protocol, Debug, Clone)]
@@ -157,7 +240,64 @@ pub struct ResultPacket(
 pub struct DisconnectPacket {
     /// The reason for the disconnection.
     pub reason: String,
-    /// Asks the peer to reconnect after a certain amount of time.
+    /// Asks the peer to reconnect after a certain amount of milliseconds.
     /// None if we never want to hear about that peer again.
     pub try_again_in: Option<u32>,
 }
+
+impl DisconnectPacket {
+    pub fn new(reason: impl Into<String>, try_again_in: Option<Duration>) -> Self {
+        DisconnectPacket {
+            reason: reason.into(),
+            try_again_in: try_again_in.map(|d| d.as_millis().min(u32::MAX as u128) as u32),
+        }
+    }
+}
+
+#[derive(Protocol, Debug, Clone)]
+pub struct RejectionPacket {
+    pub reason: RejectionReason,
+    /// Milliseconds after which the request might be accepted.
+    /// None if it will keep being rejected.
+    pub retry_in: Option<u32>,
+}
+
+impl RejectionPacket {
+    pub fn new(reason: RejectionReason, retry_in: Option<Duration>) -> Self {
+        RejectionPacket {
+            reason,
+            retry_in: retry_in.map(|d| d.as_millis().min(u32::MAX as u128) as u32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_changes_are_sent_as_flipped_bits() {
+        let mut previous = Filter::<1000>::new();
+        for bit in (0..8000).step_by(7) {
+            previous.set_bit(bit, true);
+        }
+        let mut filter = previous.clone();
+        filter.set_bit(1, true);
+        filter.set_bit(7, false);
+
+        assert!(matches!(FilterDelta::new(None, &filter, &FilterEncoding::all()), FilterDelta::Full(_)));
+        match FilterDelta::new(Some(&previous), &filter, &FilterEncoding::all()) {
+            FilterDelta::Flipped { bits, set_bits } => {
+                assert_eq!(bits, vec![1, 7]);
+                assert_eq!(set_bits as usize, filter.count_set_bits());
+            },
+            FilterDelta::Full(_) => panic!("a two-bit change was sent as a full filter"),
+        }
+
+        let mut filter = Filter::<1000>::new();
+        for bit in (0..8000).step_by(3) {
+            filter.set_bit(bit, true);
+        }
+        assert!(matches!(FilterDelta::new(Some(&previous), &filter, &FilterEncoding::all()), FilterDelta::Full(_)));
+    }
+}
@@ -1,13 +1,19 @@
 pub use crate::{
-    behaviour::KamilataBehaviour,
+    behaviour::{KamilataBehaviour, KamilataEvent},
+    capabilities::*,
     config::*,
     control::{
-        FixedSearchPriority, OngoingSearchController, SearchConfig, SearchPriority, SearchResults,
+        FixedSearchPriority, OngoingSearchController, SearchConfig, SearchPriority, SearchResults, SearchUpdate, SearchEvent, SearchFinishReason,
     },
     filters::*,
+    hashing::*,
     queries::*,
     store::*,
+    tokenizer::*,
+    topology::*,
 };
+#[cfg(feature = "memory-store")]
+pub use crate::memory_store::*;
 pub(crate) use crate::{
     behaviour::*, control::*, counter::*, db::*, handler::*, handler_proto::*, packets::*, tasks::*,
 };
@@ -20,7 +26,7 @@ pub(crate) use futures::{
 pub(crate) use libp2p::{
     core::{upgrade::DeniedUpgrade, ConnectedPoint, Endpoint, UpgradeInfo},
     swarm::{
-        derive_prelude::FromSwarm, handler::ConnectionEvent, ConnectionDenied, ConnectionHandler,
+        derive_prelude::FromSwarm, handler::ConnectionEvent, CloseConnection, ConnectionDenied, ConnectionHandler,
         ConnectionHandlerEvent, ConnectionId, KeepAlive, Stream, NetworkBehaviour,
         PollParameters, SubstreamProtocol, THandler, THandlerOutEvent, ToSwarm,
     },
@@ -32,14 +38,15 @@ pub(crate) use std::{
     collections::{BTreeMap, HashMap},
     io::Error as ioError,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Weak},
     task::{Context, Poll},
     time::{Duration, Instant},
 };
 pub(crate) use tokio::{
     sync::{
         mpsc::*,
-        oneshot::{channel as oneshot_channel, Sender as OneshotSender},
+        oneshot::{channel as oneshot_channel, Receiver as OneshotReceiver, Sender as OneshotSender},
+        watch::{self, Receiver as WatchReceiver},
         RwLock,
     },
     time::{sleep, timeout},
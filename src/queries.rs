@@ -15,3 +15,533 @@ pub trait SearchQuery<const N: usize>: Clone + Sized + Send + Sync {
     fn to_bytes(&self) -> Vec<u8>;
     fn from_bytes(bytes: &[u8]) -> Result<Self, Self::ParsingError>;
 }
+
+/// A node of a [BooleanQuery] tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryNode {
+    /// A word that must be present in the filter, contributing `weight` to the score when it is
+    Term {
+        word: String,
+        weight: u32,
+    },
+    /// Matches if all children match
+    And(Vec<QueryNode>),
+    /// Matches if at least one child matches
+    Or(Vec<QueryNode>),
+    /// Matches if the child doesn't match
+    Not(Box<QueryNode>),
+}
+
+impl QueryNode {
+    /// Builds a term with a weight of 1.
+    pub fn term(word: impl Into<String>) -> QueryNode {
+        QueryNode::Term { word: word.into(), weight: 1 }
+    }
+
+    pub fn weighted_term(word: impl Into<String>, weight: u32) -> QueryNode {
+        QueryNode::Term { word: word.into(), weight }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(node: QueryNode) -> QueryNode {
+        QueryNode::Not(Box::new(node))
+    }
+
+    /// Returns the words of the terms that contribute to the score, ignoring negated ones.
+    /// A document can only match if it contains at least one of them.
+    pub fn positive_terms(&self) -> Vec<&str> {
+        match self {
+            QueryNode::Term { word, .. } => vec![word.as_str()],
+            QueryNode::And(children) | QueryNode::Or(children) => children.iter().flat_map(|child| child.positive_terms()).collect(),
+            QueryNode::Not(_) => Vec::new(),
+        }
+    }
+
+    /// Evaluates the node.
+    /// Returns `None` if it doesn't match, or the score and the number of matching terms otherwise.
+    /// Negations are neutral unless `exclude` is set.
+    fn evaluate(&self, contains: &impl Fn(&str) -> bool, exclude: bool) -> Option<(u32, usize)> {
+        match self {
+            QueryNode::Term { word, weight } => contains(word).then_some((*weight, 1)),
+            QueryNode::And(children) => children.iter().try_fold((0u32, 0usize), |(score, matching), child| {
+                child.evaluate(contains, exclude).map(|(s, m)| (score.saturating_add(s), matching + m))
+            }),
+            QueryNode::Or(children) => children.iter().filter_map(|child| child.evaluate(contains, exclude)).reduce(|(s1, m1), (s2, m2)| (s1.saturating_add(s2), m1 + m2)),
+            QueryNode::Not(_) if !exclude => Some((0, 0)),
+            QueryNode::Not(child) => match child.evaluate(contains, exclude) {
+                Some(_) => None,
+                None => Some((0, 0)),
+            },
+        }
+    }
+
+    fn write_bytes(&self, buffer: &mut Vec<u8>) {
+        match self {
+            QueryNode::Term { word, weight } => {
+                buffer.push(0);
+                push_varint(buffer, *weight as usize);
+                push_varint(buffer, word.len());
+                buffer.extend_from_slice(word.as_bytes());
+            },
+            QueryNode::And(children) | QueryNode::Or(children) => {
+                buffer.push(if matches!(self, QueryNode::And(_)) { 1 } else { 2 });
+                push_varint(buffer, children.len());
+                for child in children {
+                    child.write_bytes(buffer);
+                }
+            },
+            QueryNode::Not(child) => {
+                buffer.push(3);
+                child.write_bytes(buffer);
+            },
+        }
+    }
+
+    fn read_bytes(data: &mut &[u8], depth: usize) -> Result<QueryNode, QueryParsingError> {
+        if depth > MAX_QUERY_DEPTH {
+            return Err(QueryParsingError::TooDeep);
+        }
+        let (&tag, remaining) = data.split_first().ok_or(QueryParsingError::InvalidBytes)?;
+        *data = remaining;
+        match tag {
+            0 => {
+                let weight = read_varint(data)?.try_into().map_err(|_| QueryParsingError::InvalidBytes)?;
+                let len = read_varint(data)?;
+                let word = String::from_utf8(read_bytes(data, len)?.to_vec()).map_err(|_| QueryParsingError::InvalidBytes)?;
+                Ok(QueryNode::Term { word, weight })
+            },
+            1 | 2 => {
+                let count = read_varint(data)?;
+                let mut children = Vec::new();
+                for _ in 0..count {
+                    children.push(QueryNode::read_bytes(data, depth + 1)?);
+                }
+                match tag {
+                    1 => Ok(QueryNode::And(children)),
+                    _ => Ok(QueryNode::Or(children)),
+                }
+            },
+            3 => Ok(QueryNode::not(QueryNode::read_bytes(data, depth + 1)?)),
+            _ => Err(QueryParsingError::InvalidBytes),
+        }
+    }
+}
+
+/// Maximum nesting of [QueryNode]s, so that malicious queries can't overflow the stack.
+const MAX_QUERY_DEPTH: usize = 32;
+
+/// Version of the binary format used by [BooleanQuery::to_bytes].
+const BOOLEAN_QUERY_FORMAT: u8 = 1;
+
+/// A ready-to-use [SearchQuery] supporting AND, OR and NOT operators as well as weighted terms.
+/// 
+/// It can be parsed from text, where terms are implicitly joined with AND:
+/// 
+/// ```
+/// # use kamilata::{queries::BooleanQuery, store::Store};
+/// fn rust_query<S: Store<125>>() -> BooleanQuery<S> {
+///     BooleanQuery::parse("rust AND (p2p OR libp2p^2) -java").unwrap()
+/// }
+/// ```
+/// 
+/// The score of a matching filter is the sum of the weights of the terms that matched.
+/// A query made only of negations therefore never matches, as its score is always 0.
+/// Negations only exclude documents: filters are still scored on their positive terms.
+/// 
+/// The store type parameter determines how words are hashed (see [Store::hash_word]).
+pub struct BooleanQuery<S> {
+    root: QueryNode,
+    min_matching: usize,
+    _store: std::marker::PhantomData<fn() -> S>,
+}
+
+impl<S> BooleanQuery<S> {
+    pub fn new(root: QueryNode) -> BooleanQuery<S> {
+        BooleanQuery {
+            root,
+            min_matching: 0,
+            _store: std::marker::PhantomData,
+        }
+    }
+
+    /// Requires at least `min_matching` terms to be present for a filter to match, in addition to the boolean operators being satisfied.
+    pub fn with_min_matching(self, min_matching: usize) -> BooleanQuery<S> {
+        BooleanQuery {
+            min_matching,
+            ..self
+        }
+    }
+
+    /// Parses a query such as `rust AND (p2p OR libp2p) -java`.
+    /// 
+    /// - Terms separated by spaces must all match (implicit AND).
+    /// - `AND` and `OR` are binary operators, AND having precedence over OR.
+    /// - `NOT term` and `-term` exclude a term or a parenthesized group.
+    /// - `term^3` gives a weight of 3 to a term (1 by default).
+    /// 
+    /// Terms are normalized with the [tokenizer](Store::TOKENIZER) of the store, like its documents.
+    pub fn parse<const N: usize>(text: &str) -> Result<BooleanQuery<S>, QueryParsingError> where S: Store<N> {
+        BooleanQuery::parse_with(text, S::TOKENIZER)
+    }
+
+    /// Parses a query like [BooleanQuery::parse], normalizing terms with the given tokenizer.
+    /// 
+    /// A term the tokenizer splits into multiple words requires all of them.
+    /// A term the tokenizer omits, such as a stop word, is ignored.
+    pub fn parse_with(text: &str, tokenizer: &dyn Tokenizer) -> Result<BooleanQuery<S>, QueryParsingError> {
+        let tokens = tokenize(text)?;
+        let mut parser = QueryParser { tokens: &tokens, position: 0, tokenizer };
+        let root = parser.parse_or(0)?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(QueryParsingError::UnexpectedToken(token.to_string()));
+        }
+        Ok(BooleanQuery::new(root.ok_or(QueryParsingError::Empty)?))
+    }
+
+    pub fn root(&self) -> &QueryNode {
+        &self.root
+    }
+
+    pub fn min_matching(&self) -> usize {
+        self.min_matching
+    }
+
+    /// Encodes the query in a compact binary format that is stable across versions.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![BOOLEAN_QUERY_FORMAT];
+        push_varint(&mut bytes, self.min_matching);
+        self.root.write_bytes(&mut bytes);
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<BooleanQuery<S>, QueryParsingError> {
+        let (&format, remaining) = bytes.split_first().ok_or(QueryParsingError::InvalidBytes)?;
+        if format != BOOLEAN_QUERY_FORMAT {
+            return Err(QueryParsingError::InvalidBytes);
+        }
+        bytes = remaining;
+        let min_matching = read_varint(&mut bytes)?;
+        let root = QueryNode::read_bytes(&mut bytes, 0)?;
+        if !bytes.is_empty() {
+            return Err(QueryParsingError::InvalidBytes);
+        }
+        Ok(BooleanQuery::new(root).with_min_matching(min_matching))
+    }
+
+    /// Computes the score of a document, given a function telling if a word is present in it.
+    /// Returns 0 if the query doesn't match.
+    pub fn score_with(&self, contains: impl Fn(&str) -> bool) -> u32 {
+        self.score(&contains, true)
+    }
+
+    /// Computes the score of a filter, given a function telling if a word might be present in it.
+    /// Negations are ignored, as the filter may aggregate documents that don't contain the excluded words.
+    pub fn score_filter_with(&self, contains: impl Fn(&str) -> bool) -> u32 {
+        self.score(&contains, false)
+    }
+
+    fn score(&self, contains: &impl Fn(&str) -> bool, exclude: bool) -> u32 {
+        match self.root.evaluate(contains, exclude) {
+            Some((score, matching)) if matching >= self.min_matching => score,
+            _ => 0,
+        }
+    }
+}
+
+impl<S> Clone for BooleanQuery<S> {
+    fn clone(&self) -> Self {
+        BooleanQuery {
+            root: self.root.clone(),
+            min_matching: self.min_matching,
+            _store: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for BooleanQuery<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BooleanQuery").field("root", &self.root).field("min_matching", &self.min_matching).finish()
+    }
+}
+
+impl<const N: usize, S: Store<N>> SearchQuery<N> for BooleanQuery<S> {
+    type ParsingError = QueryParsingError;
+
+    fn match_score(&self, filter: &Filter<N>) -> u32 {
+        self.score_filter_with(|word| filter.get_word::<S>(word))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        BooleanQuery::to_bytes(self)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Self::ParsingError> {
+        BooleanQuery::from_bytes(bytes)
+    }
+}
+
+/// Error returned when a [BooleanQuery] couldn't be parsed from text or decoded from bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryParsingError {
+    /// The query contains no term
+    Empty,
+    /// The query ended while a term was expected
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    InvalidWeight(String),
+    /// Parentheses are nested too deeply
+    TooDeep,
+    /// The binary representation is invalid
+    InvalidBytes,
+}
+
+impl std::fmt::Display for QueryParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryParsingError::Empty => write!(f, "query is empty"),
+            QueryParsingError::UnexpectedEnd => write!(f, "unexpected end of query"),
+            QueryParsingError::UnexpectedToken(token) => write!(f, "unexpected token {token:?}"),
+            QueryParsingError::InvalidWeight(weight) => write!(f, "invalid weight {weight:?}"),
+            QueryParsingError::TooDeep => write!(f, "query is nested more than {MAX_QUERY_DEPTH} times"),
+            QueryParsingError::InvalidBytes => write!(f, "invalid binary query"),
+        }
+    }
+}
+
+impl std::error::Error for QueryParsingError {}
+
+impl From<FilterDecodingError> for QueryParsingError {
+    fn from(_: FilterDecodingError) -> Self {
+        QueryParsingError::InvalidBytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String, u32),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word, 1) => write!(f, "{word}"),
+            Token::Word(word, weight) => write!(f, "{word}^{weight}"),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryParsingError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '-' => tokens.push(Token::Not),
+            c => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let (word, weight) = match word.split_once('^') {
+                    Some((word, weight)) => (word.to_string(), weight.parse().map_err(|_| QueryParsingError::InvalidWeight(weight.to_string()))?),
+                    None => (word, 1),
+                };
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "" => return Err(QueryParsingError::UnexpectedToken(format!("^{weight}"))),
+                    _ => Token::Word(word, weight),
+                });
+            },
+        }
+    }
+    if tokens.is_empty() {
+        return Err(QueryParsingError::Empty);
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser for [BooleanQuery::parse].
+/// Parses tokens into a tree.
+/// Parsing methods return `None` for parts of the query made only of ignored terms.
+struct QueryParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    tokenizer: &'a dyn Tokenizer,
+}
+
+impl QueryParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn parse_or(&mut self, depth: usize) -> Result<Option<QueryNode>, QueryParsingError> {
+        let mut children = Vec::from_iter(self.parse_and(depth)?);
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            children.extend(self.parse_and(depth)?);
+        }
+        Ok(combine(children, QueryNode::Or))
+    }
+
+    fn parse_and(&mut self, depth: usize) -> Result<Option<QueryNode>, QueryParsingError> {
+        let mut children = Vec::from_iter(self.parse_unary(depth)?);
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    children.extend(self.parse_unary(depth)?);
+                },
+                Some(Token::Word(..) | Token::Not | Token::Open) => children.extend(self.parse_unary(depth)?),
+                _ => break,
+            }
+        }
+        Ok(combine(children, QueryNode::And))
+    }
+
+    fn parse_unary(&mut self, depth: usize) -> Result<Option<QueryNode>, QueryParsingError> {
+        if depth > MAX_QUERY_DEPTH {
+            return Err(QueryParsingError::TooDeep);
+        }
+        match self.next().cloned() {
+            Some(Token::Word(word, weight)) => {
+                let terms = self.tokenizer.tokenize(&word).into_iter().map(|word| QueryNode::Term { word, weight }).collect();
+                Ok(combine(terms, QueryNode::And))
+            },
+            Some(Token::Not) => Ok(self.parse_unary(depth + 1)?.map(QueryNode::not)),
+            Some(Token::Open) => {
+                let node = self.parse_or(depth + 1)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(node),
+                    Some(token) => Err(QueryParsingError::UnexpectedToken(token.to_string())),
+                    None => Err(QueryParsingError::UnexpectedEnd),
+                }
+            },
+            Some(token) => Err(QueryParsingError::UnexpectedToken(token.to_string())),
+            None => Err(QueryParsingError::UnexpectedEnd),
+        }
+    }
+}
+
+/// Joins nodes with an operator, unless there are less than two of them.
+fn combine(mut children: Vec<QueryNode>, operator: fn(Vec<QueryNode>) -> QueryNode) -> Option<QueryNode> {
+    match children.len() {
+        0 => None,
+        1 => children.pop(),
+        _ => Some(operator(children)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Query = BooleanQuery<()>;
+
+    fn parse_default(text: &str) -> Result<Query, QueryParsingError> {
+        Query::parse_with(text, &UnicodeTokenizer::DEFAULT)
+    }
+
+    fn score(query: &Query, words: &[&str]) -> u32 {
+        query.score_with(|word| words.contains(&word))
+    }
+
+    #[test]
+    fn parse() {
+        let query = parse_default("rust AND (p2p OR libp2p) -java").unwrap();
+        assert_eq!(query.root(), &QueryNode::And(vec![
+            QueryNode::term("rust"),
+            QueryNode::Or(vec![QueryNode::term("p2p"), QueryNode::term("libp2p")]),
+            QueryNode::not(QueryNode::term("java")),
+        ]));
+
+        let query = parse_default("Rust^3 OR go NOT c").unwrap();
+        assert_eq!(query.root(), &QueryNode::Or(vec![
+            QueryNode::weighted_term("rust", 3),
+            QueryNode::And(vec![QueryNode::term("go"), QueryNode::not(QueryNode::term("c"))]),
+        ]));
+
+        let query = parse_default("Café p2p-network").unwrap();
+        assert_eq!(query.root(), &QueryNode::And(vec![
+            QueryNode::term("cafe"),
+            QueryNode::And(vec![QueryNode::term("p2p"), QueryNode::term("network")]),
+        ]));
+
+        let tokenizer = UnicodeTokenizer::new().with_stop_words(Language::English.stop_words());
+        let query = Query::parse_with("the rust OR (of -the)", &tokenizer).unwrap();
+        assert_eq!(query.root(), &QueryNode::term("rust"));
+        assert_eq!(Query::parse_with("the", &tokenizer).unwrap_err(), QueryParsingError::Empty);
+
+        assert_eq!(parse_default("  ").unwrap_err(), QueryParsingError::Empty);
+        assert_eq!(parse_default("rust AND").unwrap_err(), QueryParsingError::UnexpectedEnd);
+        assert_eq!(parse_default("(rust").unwrap_err(), QueryParsingError::UnexpectedEnd);
+        assert_eq!(parse_default("rust)").unwrap_err(), QueryParsingError::UnexpectedToken(String::from(")")));
+        assert_eq!(parse_default("rust^x").unwrap_err(), QueryParsingError::InvalidWeight(String::from("x")));
+        assert_eq!(parse_default(&"(".repeat(100)).unwrap_err(), QueryParsingError::TooDeep);
+    }
+
+    #[test]
+    fn match_score() {
+        let query = parse_default("rust AND (p2p OR libp2p^2) -java").unwrap();
+        assert_eq!(score(&query, &["rust", "p2p"]), 2);
+        assert_eq!(score(&query, &["rust", "p2p", "libp2p"]), 4);
+        assert_eq!(score(&query, &["rust"]), 0);
+        assert_eq!(score(&query, &["rust", "p2p", "java"]), 0);
+
+        let query = parse_default("a OR b OR c").unwrap().with_min_matching(2);
+        assert_eq!(score(&query, &["a"]), 0);
+        assert_eq!(score(&query, &["a", "c"]), 2);
+
+        let query = parse_default("-java").unwrap();
+        assert_eq!(score(&query, &[]), 0);
+    }
+
+    #[test]
+    fn filter_score_ignores_negations() {
+        let query = parse_default("rust AND (p2p OR libp2p^2) -java").unwrap();
+        assert_eq!(query.score_filter_with(|word| ["rust", "p2p", "java"].contains(&word)), 2);
+        assert_eq!(query.score_filter_with(|word| ["rust", "java"].contains(&word)), 0);
+
+        let query = parse_default("rust NOT (go OR java)").unwrap();
+        assert_eq!(query.score_filter_with(|word| ["rust", "go", "java"].contains(&word)), 1);
+        assert_eq!(query.score_with(|word| ["rust", "go", "java"].contains(&word)), 0);
+    }
+
+    #[test]
+    fn bytes() {
+        let query = parse_default("rust AND (p2p OR libp2p^2) -java").unwrap().with_min_matching(2);
+        let bytes = query.to_bytes();
+        let decoded = Query::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.root(), query.root());
+        assert_eq!(decoded.min_matching(), 2);
+
+        for len in 0..bytes.len() {
+            assert!(Query::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut too_deep = vec![BOOLEAN_QUERY_FORMAT, 0];
+        too_deep.extend([3; 100]);
+        assert_eq!(Query::from_bytes(&too_deep).unwrap_err(), QueryParsingError::TooDeep);
+    }
+}
@@ -19,16 +19,29 @@ pub type ResultStreamBuilderFut<SearchResult> = Pin<Box<dyn Future<Output = Resu
 pub trait Store<const N: usize>: Send + Sync + 'static {
     type Result: SearchResult + Send + Sync;
     type Query: SearchQuery<N>;
+
+    /// The hasher used to turn words into bit indices.
+    /// Its [identity](WordHasher::identity) is sent to seeders, which refuse to send filters to peers hashing differently.
+    /// 
+    /// Override it to change the number of hashes or to use a private network seed.
+    const HASHER: &'static dyn WordHasher = &DoubleHasher::DEFAULT;
+
+    /// The tokenizer used to split documents into words.
+    /// Queries must be tokenized the same way, which [BooleanQuery::parse] does.
+    const TOKENIZER: &'static dyn Tokenizer = &UnicodeTokenizer::DEFAULT;
     
-    /// Hash a word the way you like.
-    /// You can return multiple hashes for a single input (it's the idea behind Bloom filters).
+    /// Hashes a word using [Store::HASHER].
     /// 
+    /// Stores overriding this should also override [Store::HASHER] with a hasher describing their scheme.
     /// Must return at least one value.
     /// Must return values lower than `N*8` as they will be used as bit indices in filters.
-    fn hash_word(word: &str) -> Vec<usize>;
+    fn hash_word(word: &str) -> Vec<usize> {
+        Self::HASHER.hash_word(word, N * 8)
+    }
 
     /// Return a filter that has been filled with the words of the documents.
     /// This function is intented to return a cached value as the filter should have been generated earlier.
+    /// Stores that remove documents can maintain a [CountingFilter] and return its [projection](CountingFilter::as_filter).
     async fn get_filter(&self) -> Filter<N>; // TODO: use reference?
 
     /// Search among all documents and return those matching at least `min_matching` words.
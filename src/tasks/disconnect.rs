@@ -0,0 +1,76 @@
+//! This module contains the tasks responsible for gracefully ending our relationship with a remote peer.
+
+use super::*;
+
+/// How long we wait for the remote peer to acknowledge our [DisconnectPacket] before closing the connection anyway
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends a [DisconnectPacket] through a new substream and then closes the connection.
+/// The connection is closed once the remote peer closed the substream, so that the packet isn't lost.
+pub(crate) async fn send_disconnect<const N: usize, S: Store<N>>(
+    mut stream: KamOutStreamSink<Stream>,
+    packet: DisconnectPacket,
+    db: Arc<Db<N, S>>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) -> HandlerTaskOutput {
+    debug!("{our_peer_id} Disconnecting {remote_peer_id}: {} (try_again_in: {:?})", packet.reason, packet.try_again_in);
+
+    match stream.start_send_unpin(RequestPacket::Disconnect(packet)) {
+        Ok(()) => {
+            if let Err(e) = stream.flush().await {
+                warn!("{our_peer_id} Error while flushing disconnect packet to {remote_peer_id}: {e}");
+            }
+        },
+        Err(e) => warn!("{our_peer_id} Error while sending disconnect packet to {remote_peer_id}: {e}"),
+    }
+    let _ = stream.close().await;
+    if timeout(DISCONNECT_TIMEOUT, stream.next()).await.is_err() {
+        debug!("{our_peer_id} {remote_peer_id} didn't close the disconnect channel in time");
+    }
+
+    db.behaviour_controller().close_connection(remote_peer_id).await;
+
+    HandlerTaskOutput::None
+}
+
+pub(crate) fn send_disconnect_boxed<const N: usize, S: Store<N>>(
+    stream: KamOutStreamSink<Stream>,
+    vals: PendingTaskParams
+) -> Pin<Box<dyn Future<Output = HandlerTaskOutput> + Send>> {
+    let vals: Box<(DisconnectPacket, Arc<Db<N, S>>, PeerId, PeerId)> = vals.downcast().unwrap(); // TODO: downcast unchecked?
+    send_disconnect(stream, vals.0, vals.1, vals.2, vals.3).boxed()
+}
+
+pub(crate) fn pending_send_disconnect<const N: usize, S: Store<N>>(
+    packet: DisconnectPacket,
+    db: Arc<Db<N, S>>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) -> PendingHandlerTask<PendingTaskParams> {
+    PendingHandlerTask {
+        params: Box::new((packet, db, our_peer_id, remote_peer_id)),
+        fut: send_disconnect_boxed::<N, S>,
+        name: "send_disconnect",
+    }
+}
+
+/// Honours a [DisconnectPacket] received from a remote peer.
+/// The peer won't be dialed nor leeched from again until it allows us to.
+pub(crate) async fn handle_disconnect_packet<const N: usize, S: Store<N>>(
+    packet: DisconnectPacket,
+    db: &Db<N, S>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) {
+    info!("{our_peer_id} {remote_peer_id} disconnected us: {} (try_again_in: {:?})", packet.reason, packet.try_again_in);
+
+    let try_again_in = packet.try_again_in.map(|ms| Duration::from_millis(ms as u64));
+    db.ban_peer(remote_peer_id, try_again_in);
+
+    db.behaviour_controller().emit_event(KamilataEvent::PeerDisconnected {
+        peer_id: remote_peer_id,
+        reason: packet.reason,
+        try_again_in,
+    }).await;
+}
@@ -2,23 +2,46 @@
 
 use super::*;
 
+/// How long we refuse to leech from a seeder that sent us invalid filters.
+const MISBEHAVING_SEEDER_BAN_DURATION: Duration = Duration::from_secs(3600);
+
 pub(crate) async fn leech_filters<const N: usize, S: Store<N>>(mut stream: KamOutStreamSink<Stream>, db: Arc<Db<N, S>>, our_peer_id: PeerId, remote_peer_id: PeerId) -> HandlerTaskOutput {
     trace!("{our_peer_id} Inbound filter refresh task executing");
 
-    // Claims a spot as a seeder for the remote peer
-    if let Err(TooManySeeders{}) = db.add_seeder(remote_peer_id).await {
-        warn!("{our_peer_id} Too many seeders, can't leech from {remote_peer_id}");
+    // Respects the wish of peers that disconnected us or refused to seed to us
+    if db.is_banned(&remote_peer_id) {
+        debug!("{our_peer_id} Not leeching from {remote_peer_id} as it disconnected us");
+        return HandlerTaskOutput::None;
+    }
+    if db.is_leeching_postponed(&remote_peer_id) {
+        debug!("{our_peer_id} Not leeching from {remote_peer_id} as it asked us to retry later");
+        return HandlerTaskOutput::None;
+    }
+    if db.is_blocked(&remote_peer_id) {
+        debug!("{our_peer_id} Not leeching from {remote_peer_id} as it is blocked");
         return HandlerTaskOutput::None;
     }
 
+    // Claims a spot as a seeder for the remote peer, held until this task ends
+    let _spot = match db.add_seeder(remote_peer_id).await {
+        Ok(spot) => spot,
+        Err(TooManySeeders{}) => {
+            warn!("{our_peer_id} Too many seeders, can't leech from {remote_peer_id}");
+            return HandlerTaskOutput::None;
+        },
+    };
+
     // Send our request
     let config = db.get_config();
-    let req = GetFiltersPacket {
-        filter_count: config.filter_count as u8,
+    let mut blocked_peers_watcher = db.watch_blocked_peers();
+    let mut req = GetFiltersPacket {
+        filter_count: config.filter_count.min(u8::MAX as usize) as u8,
         interval: config.get_filters_interval.clone(),
-        blocked_peers: Vec::new(), // TODO
+        blocked_peers: db.blocked_peers().into_iter().map(|p| p.into()).collect(),
+        encodings: FilterEncoding::all(),
+        hasher: S::HASHER.identity(),
     };
-    if let Err(e) = stream.start_send_unpin(RequestPacket::GetFilters(req)) {
+    if let Err(e) = stream.start_send_unpin(RequestPacket::GetFilters(req.clone())) {
         warn!("{our_peer_id} Error while sending get filters request to {remote_peer_id}: {e}");
         return HandlerTaskOutput::None;
     }
@@ -33,8 +56,33 @@ pub(crate) async fn leech_filters<const N: usize, S: Store<N>>(mut stream: KamOu
     }).await;
 
     // Receive filters
+    // Some tolerance is allowed on the interval as network delays vary
+    let tolerated_interval = |min_ms: usize| Duration::from_millis(min_ms as u64 * 9 / 10);
+    let mut min_update_interval = tolerated_interval(config.get_filters_interval.min());
+    let mut last_update: Option<Instant> = None;
+    let mut awaiting_full_update = false;
     loop {
-        let packet = match stream.next().await {
+        let packet = match futures::future::select(stream.next(), Box::pin(blocked_peers_watcher.changed())).await {
+            future::Either::Left((packet, _)) => packet,
+            future::Either::Right((Ok(()), _)) => {
+                // Renegotiate so that the seeder stops sending us what blocked peers provide
+                req.blocked_peers = db.blocked_peers().into_iter().map(|p| p.into()).collect();
+                debug!("{our_peer_id} Blocked peers changed, updating get filters request to {remote_peer_id}");
+                if let Err(e) = stream.start_send_unpin(RequestPacket::GetFilters(req.clone())) {
+                    warn!("{our_peer_id} Error while sending get filters request to {remote_peer_id}: {e}");
+                    return HandlerTaskOutput::None;
+                }
+                if let Err(e) = stream.flush().await {
+                    warn!("{our_peer_id} Error while flushing get filters request to {remote_peer_id}: {e}");
+                    return HandlerTaskOutput::None;
+                }
+                awaiting_full_update = true;
+                last_update = None;
+                continue;
+            },
+            future::Either::Right((Err(_), _)) => return HandlerTaskOutput::None,
+        };
+        let packet = match packet {
             Some(Ok(packet)) => packet,
             Some(Err(e)) => {
                 warn!("{our_peer_id} Error while receiving filters from {remote_peer_id}: {e}");
@@ -45,26 +93,93 @@ pub(crate) async fn leech_filters<const N: usize, S: Store<N>>(mut stream: KamOu
                 return HandlerTaskOutput::None;
             }
         };
-        let packet = match packet {
-            ResponsePacket::UpdateFilters(packet) => packet,
+        let result = match packet {
+            ResponsePacket::UpdateFiltersDelta(_) | ResponsePacket::FiltersUnchanged if awaiting_full_update => {
+                trace!("{our_peer_id} Ignoring partial filter update from {remote_peer_id} while waiting for a full one");
+                continue;
+            },
+            ResponsePacket::UpdateFilters(packet) => {
+                awaiting_full_update = false;
+                // The seeder can't negotiate an interval shorter than ours
+                min_update_interval = tolerated_interval(packet.interval.min().max(config.get_filters_interval.min()));
+                match packet.filters.iter().map(|f| f.decode()).collect::<Result<Vec<Filter<N>>, _>>() {
+                    Ok(filters) => db.set_remote_filter(remote_peer_id, filters, req.filter_count as usize).await,
+                    Err(e) => Err(FilterUpdateError::Decoding(e)),
+                }
+            },
+            ResponsePacket::UpdateFiltersDelta(packet) => db.apply_remote_filter_deltas(remote_peer_id, packet.deltas, req.filter_count as usize).await,
+            ResponsePacket::FiltersUnchanged => Ok(()),
+            ResponsePacket::Disconnect(packet) => {
+                handle_disconnect_packet(packet, &db, our_peer_id, remote_peer_id).await;
+                return HandlerTaskOutput::None;
+            },
+            ResponsePacket::Rejected(packet) => {
+                let retry_in = packet.retry_in.map(|ms| Duration::from_millis(ms as u64));
+                info!("{our_peer_id} {remote_peer_id} refused to seed to us: {} (retry_in: {retry_in:?})", packet.reason);
+                db.postpone_leeching(remote_peer_id, retry_in);
+                db.behaviour_controller().emit_event(KamilataEvent::LeechRejected {
+                    peer_id: remote_peer_id,
+                    reason: packet.reason,
+                    retry_in,
+                }).await;
+                return HandlerTaskOutput::None;
+            },
             _ => {
                 warn!("{our_peer_id} Received unexpected packet from {remote_peer_id} while waiting for filters");
                 return HandlerTaskOutput::None;
             },
         };
-        // TODO check packet.filters lenght and count and time between received
-        let filters = packet.filters.iter().map(|f| f.as_slice().into()).collect::<Vec<Filter<N>>>();
-        db.set_remote_filter(remote_peer_id, filters).await;
-        trace!("{our_peer_id} Received filters from {remote_peer_id}");
+
+        // Make sure the seeder doesn't flood us, unless we asked for the update
+        let result = result.and_then(|()| match last_update {
+            Some(last_update) if last_update.elapsed() < min_update_interval => Err(FilterUpdateError::TooFrequent {
+                elapsed_ms: last_update.elapsed().as_millis() as usize,
+                min_ms: min_update_interval.as_millis() as usize,
+            }),
+            _ => Ok(()),
+        });
+        last_update = Some(Instant::now());
+
+        match result {
+            Ok(()) => trace!("{our_peer_id} Received filters from {remote_peer_id}"),
+            Err(FilterUpdateError::DeltaMismatch) => {
+                // Our filters are out of sync so we ask for a full update
+                warn!("{our_peer_id} Filter deltas from {remote_peer_id} don't match our filters, asking for a full update");
+                if let Err(e) = stream.start_send_unpin(RequestPacket::GetFilters(req.clone())) {
+                    warn!("{our_peer_id} Error while sending get filters request to {remote_peer_id}: {e}");
+                    return HandlerTaskOutput::None;
+                }
+                if let Err(e) = stream.flush().await {
+                    warn!("{our_peer_id} Error while flushing get filters request to {remote_peer_id}: {e}");
+                    return HandlerTaskOutput::None;
+                }
+                awaiting_full_update = true;
+                last_update = None;
+            },
+            Err(error) => {
+                warn!("{our_peer_id} {remote_peer_id} sent invalid filters: {error}");
+                let packet = DisconnectPacket::new(error.to_string(), Some(MISBEHAVING_SEEDER_BAN_DURATION));
+                if stream.start_send_unpin(RequestPacket::Disconnect(packet)).is_ok() {
+                    let _ = stream.flush().await;
+                }
+                let _ = stream.close().await;
+                db.ban_peer(remote_peer_id, Some(MISBEHAVING_SEEDER_BAN_DURATION));
+                db.behaviour_controller().emit_event(KamilataEvent::SeederMisbehaved {
+                    peer_id: remote_peer_id,
+                    error,
+                }).await;
+                return HandlerTaskOutput::None;
+            },
+        }
     }
 }
 
-pub(crate) fn leech_filters_boxed<const N: usize, S: Store<N>>(stream: KamOutStreamSink<Stream>, vals: Box<dyn Any + Send>) -> Pin<Box<dyn Future<Output = HandlerTaskOutput> + Send>> {
+pub(crate) fn leech_filters_boxed<const N: usize, S: Store<N>>(stream: KamOutStreamSink<Stream>, vals: PendingTaskParams) -> Pin<Box<dyn Future<Output = HandlerTaskOutput> + Send>> {
     let vals: Box<(Arc<Db<N, S>>, PeerId, PeerId)> = vals.downcast().unwrap(); // TODO: downcast unchecked?
     leech_filters(stream, vals.0, vals.1, vals.2).boxed()
 }
 
-pub(crate) fn pending_leech_filters<const N: usize, S: Store<N>>(db: Arc<Db<N, S>>, our_peer_id: PeerId, remote_peer_id: PeerId) -> PendingHandlerTask<Box<dyn Any + Send>> {
+pub(crate) fn pending_leech_filters<const N: usize, S: Store<N>>(db: Arc<Db<N, S>>, our_peer_id: PeerId, remote_peer_id: PeerId) -> PendingHandlerTask<PendingTaskParams> {
     PendingHandlerTask {
         params: Box::new((db, our_peer_id, remote_peer_id)),
         fut: leech_filters_boxed::<N, S>,
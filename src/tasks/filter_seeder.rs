@@ -2,7 +2,24 @@
 
 use super::*;
 
-// TODO: When rejecting a peer, we should send a message to the peer explaining why we rejected it
+/// How long a leecher we had no spot for is asked to wait before trying again
+const FULL_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Tells the remote peer why we won't seed to it.
+async fn reject_leecher(
+    mut stream: KamInStreamSink<Stream>,
+    reason: RejectionReason,
+    retry_in: Option<Duration>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) -> HandlerTaskOutput {
+    warn!("{our_peer_id} Rejected {remote_peer_id} as a leecher: {reason}");
+    if stream.start_send_unpin(ResponsePacket::Rejected(RejectionPacket::new(reason, retry_in))).is_ok() {
+        let _ = stream.flush().await;
+    }
+    let _ = stream.close().await;
+    HandlerTaskOutput::None
+}
 
 pub(crate) async fn seed_filters<const N: usize, S: Store<N>>(
     mut stream: KamInStreamSink<Stream>,
@@ -13,50 +30,137 @@ pub(crate) async fn seed_filters<const N: usize, S: Store<N>>(
 ) -> HandlerTaskOutput {
     trace!("{our_peer_id} Seed filters task executing");
 
+    // Our filters are meaningless to peers hashing words differently
+    if req.hasher != S::HASHER.identity() {
+        debug!("{our_peer_id} {remote_peer_id} hashes words differently (ours: {:?}, theirs: {:?})", S::HASHER.identity(), req.hasher);
+        return reject_leecher(stream, RejectionReason::HasherMismatch, None, our_peer_id, remote_peer_id).await;
+    }
+
     // Checks if we should allow this peer to leech
-    if let Some(approve_leecher) = &db.get_config().approve_leecher {
+    if db.is_blocked(&remote_peer_id) {
+        return reject_leecher(stream, RejectionReason::Blocked, None, our_peer_id, remote_peer_id).await;
+    }
+    let config = db.get_config();
+    if let Some(approve_leecher) = &config.approve_leecher {
         if !approve_leecher(remote_peer_id).await {
-            warn!("{our_peer_id} {remote_peer_id} wasn't approved to leech");
-            return HandlerTaskOutput::None;
+            return reject_leecher(stream, RejectionReason::NotApproved, None, our_peer_id, remote_peer_id).await;
         }
     }
-    
-    // Claims a spot as a leecher for the remote peer
-    if let Err(TooManyLeechers{}) = db.add_leecher(remote_peer_id).await {
-        warn!("{our_peer_id} Too many leechers, can't seed to {remote_peer_id}");
-        return HandlerTaskOutput::None;
-    }
 
     // Determine an interval
-    let config = db.get_config();
-    req.filter_count = req.filter_count.clamp(0, config.filter_count as u8); // unsafe cast
-    let interval = match config.get_filters_interval.intersection(&req.interval) {
-        Some(interval) => interval.target() as u64,
+    req.filter_count = req.filter_count.min(config.filter_count.min(u8::MAX as usize) as u8);
+    let negotiated_interval = match config.get_filters_interval.intersection(&req.interval) {
+        Some(interval) => interval,
         None => {
-            warn!("{our_peer_id} Couldn't agree on interval with {remote_peer_id} (ours: {:?}, theirs: {:?})", config.get_filters_interval, req.interval);
-            return HandlerTaskOutput::None;
+            debug!("{our_peer_id} Couldn't agree on interval with {remote_peer_id} (ours: {:?}, theirs: {:?})", config.get_filters_interval, req.interval);
+            return reject_leecher(stream, RejectionReason::IntervalMismatch, None, our_peer_id, remote_peer_id).await;
         }
     };
+    let min_interval = Duration::from_millis(negotiated_interval.min() as u64);
+    let interval = Duration::from_millis(negotiated_interval.target() as u64);
+
+    // Claims a spot as a leecher for the remote peer
+    let priority = config.leecher_policy.as_ref().map(|policy| policy.priority(remote_peer_id)).unwrap_or(0);
+    let mut preempted = match db.add_leecher(remote_peer_id, priority).await {
+        Ok(preempted) => preempted,
+        Err(TooManyLeechers{}) => return reject_leecher(stream, RejectionReason::TooManyLeechers, Some(FULL_RETRY_DELAY), our_peer_id, remote_peer_id).await,
+    };
 
     // Send an event
     db.behaviour_controller().emit_event(KamilataEvent::LeecherAdded {
         peer_id: remote_peer_id,
         filter_count: req.filter_count as usize,
-        interval_ms: interval as usize,
+        interval_ms: interval.as_millis() as usize,
     }).await;
 
-    let mut peers_to_ignore = req.blocked_peers.to_libp2p_peer_ids();
+    // Only use encodings the leecher can decode
+    let encodings = FilterEncoding::all().into_iter().filter(|e| req.encodings.contains(e)).collect::<Vec<_>>();
+
+    let mut peers_to_ignore = req.blocked_peers.clone().to_libp2p_peer_ids();
     peers_to_ignore.push(remote_peer_id);
 
+    // Filters the leecher is known to have, so that we only send what changed
+    let mut last_sent: Option<Vec<Filter<N>>> = None;
+    let mut last_sent_at = Instant::now();
+    let mut store_changes = db.watch_store_changes();
+    let mut store_changed = false;
+
     loop {
-        let our_filters = db.get_filters_bytes(&peers_to_ignore).await; // FIXME: filter count isn't respected
-        stream.start_send_unpin(ResponsePacket::UpdateFilters(UpdateFiltersPacket { filters: our_filters })).unwrap();
-        if stream.flush().await.is_err() {
-            warn!("{our_peer_id} Couldn't send filters to {remote_peer_id}");
-            return HandlerTaskOutput::None;
-        } 
-        trace!("{our_peer_id} Sent filters to {remote_peer_id}");
-
-        sleep(Duration::from_millis(interval)).await;
+        let our_filters = db.get_filters(&peers_to_ignore, req.filter_count as usize).await;
+        let packet = match &last_sent {
+            Some(last_sent) if *last_sent == our_filters && store_changed => {
+                trace!("{our_peer_id} Store changed but filters for {remote_peer_id} didn't");
+                None
+            },
+            Some(last_sent) if *last_sent == our_filters => Some(ResponsePacket::FiltersUnchanged),
+            Some(last_sent) => Some(ResponsePacket::UpdateFiltersDelta(UpdateFiltersDeltaPacket {
+                deltas: our_filters.iter().enumerate().map(|(level, f)| FilterDelta::new(last_sent.get(level), f, &encodings)).collect(),
+            })),
+            None => Some(ResponsePacket::UpdateFilters(UpdateFiltersPacket {
+                filters: our_filters.iter().map(|f| EncodedFilter::new(f, &encodings)).collect(),
+                interval: negotiated_interval.clone(),
+            })),
+        };
+        if let Some(packet) = packet {
+            stream.start_send_unpin(packet).unwrap();
+            if stream.flush().await.is_err() {
+                warn!("{our_peer_id} Couldn't send filters to {remote_peer_id}");
+                return HandlerTaskOutput::None;
+            } 
+            trace!("{our_peer_id} Sent filters to {remote_peer_id}");
+            last_sent = Some(our_filters);
+            last_sent_at = Instant::now();
+        }
+        store_changed = false;
+
+        // Wait for the next update, unless the store changes or the leecher updates its request before
+        let tick = Box::pin(sleep(interval.saturating_sub(last_sent_at.elapsed())));
+        let next_event = futures::future::select(futures::future::select(tick, Box::pin(store_changes.changed())), stream.next());
+        let next_event = match futures::future::select(next_event, &mut preempted).await {
+            future::Either::Left((next_event, _)) => next_event,
+            future::Either::Right((Ok(()), _)) => {
+                // A peer with a higher priority took the spot
+                info!("{our_peer_id} {remote_peer_id} was preempted by a leecher with a higher priority");
+                let retry_in = config.leecher_policy.as_ref().and_then(|policy| policy.retry_delay());
+                return reject_leecher(stream, RejectionReason::Preempted, retry_in, our_peer_id, remote_peer_id).await;
+            },
+            future::Either::Right((Err(_), _)) => {
+                // The peer asked for our filters again on another stream, which took the spot over
+                debug!("{our_peer_id} {remote_peer_id} is now leeching through another stream");
+                let _ = stream.close().await;
+                return HandlerTaskOutput::None;
+            },
+        };
+        match next_event {
+            future::Either::Left((future::Either::Left(_), _)) => (),
+            future::Either::Left((future::Either::Right((Ok(()), _)), _)) => {
+                // Push the new filters as soon as the leecher allows it
+                store_changed = true;
+                sleep(min_interval.saturating_sub(last_sent_at.elapsed())).await;
+            },
+            future::Either::Left((future::Either::Right((Err(_), _)), _)) => return HandlerTaskOutput::None,
+            future::Either::Right((Some(Ok(RequestPacket::GetFilters(new_req))), _)) => {
+                debug!("{our_peer_id} {remote_peer_id} asked for a full filter update");
+                req.filter_count = new_req.filter_count.min(config.filter_count.min(u8::MAX as usize) as u8);
+                peers_to_ignore = new_req.blocked_peers.to_libp2p_peer_ids();
+                peers_to_ignore.push(remote_peer_id);
+                last_sent = None;
+            },
+            future::Either::Right((Some(Ok(RequestPacket::Disconnect(packet))), _)) => {
+                handle_disconnect_packet(packet, &db, our_peer_id, remote_peer_id).await;
+                return HandlerTaskOutput::None;
+            },
+            future::Either::Right((Some(Ok(packet)), _)) => {
+                warn!("{our_peer_id} Received unexpected packet from {remote_peer_id} while seeding filters: {packet:?}");
+            },
+            future::Either::Right((Some(Err(e)), _)) => {
+                warn!("{our_peer_id} Error while receiving packet from {remote_peer_id} while seeding filters: {e}");
+                return HandlerTaskOutput::None;
+            },
+            future::Either::Right((None, _)) => {
+                debug!("{our_peer_id} Seed filters channel was closed by {remote_peer_id}");
+                return HandlerTaskOutput::None;
+            },
+        }
     }
 }
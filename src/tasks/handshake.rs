@@ -0,0 +1,134 @@
+//! This module contains the tasks exchanging [Capabilities] with a newly connected peer.
+
+use super::*;
+
+/// How long we wait for the remote peer to answer our handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long we refuse to dial a peer we can't talk to.
+const INCOMPATIBLE_PEER_BAN_DURATION: Duration = Duration::from_secs(3600);
+
+/// Progress of the handshake of a connection.
+/// Other requests are only made and answered once it [succeeded](HandshakeState::Succeeded).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HandshakeState {
+    Pending,
+    Succeeded,
+    /// The peer is incompatible or didn't complete the handshake
+    Failed,
+}
+
+/// Waits for the handshake to complete, returning true if it succeeded.
+/// Gives up if the peer didn't start it in time.
+pub(crate) async fn wait_for_handshake(mut handshake: WatchReceiver<HandshakeState>) -> bool {
+    let result = timeout(HANDSHAKE_TIMEOUT, handshake.wait_for(|state| *state != HandshakeState::Pending)).await;
+    matches!(result, Ok(Ok(state)) if *state == HandshakeState::Succeeded)
+}
+
+/// Sends our capabilities to the peer we dialed and checks the ones it answers with.
+pub(crate) async fn handshake<const N: usize, S: Store<N>>(
+    mut stream: KamOutStreamSink<Stream>,
+    db: Arc<Db<N, S>>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) -> HandlerTaskOutput {
+    trace!("{our_peer_id} Handshake task executing");
+
+    let ours = Capabilities::of::<N, S>();
+    let failed = HandlerTaskOutput::HandshakeFinished { compatible: false };
+    if let Err(e) = stream.start_send_unpin(RequestPacket::Handshake(ours.clone())) {
+        warn!("{our_peer_id} Error while sending handshake to {remote_peer_id}: {e}");
+        return failed;
+    }
+    if let Err(e) = stream.flush().await {
+        warn!("{our_peer_id} Error while flushing handshake to {remote_peer_id}: {e}");
+        return failed;
+    }
+
+    let theirs = match timeout(HANDSHAKE_TIMEOUT, stream.next()).await {
+        Ok(Some(Ok(ResponsePacket::Handshake(theirs)))) => theirs,
+        Ok(Some(Ok(packet))) => {
+            warn!("{our_peer_id} Received unexpected packet from {remote_peer_id} during handshake: {packet:?}");
+            return failed;
+        },
+        Ok(Some(Err(e))) => {
+            warn!("{our_peer_id} Error while receiving handshake from {remote_peer_id}: {e}");
+            return failed;
+        },
+        Ok(None) => {
+            warn!("{our_peer_id} Handshake channel was closed by {remote_peer_id}");
+            return failed;
+        },
+        Err(_) => {
+            warn!("{our_peer_id} {remote_peer_id} didn't answer our handshake");
+            return failed;
+        },
+    };
+    let _ = stream.close().await;
+
+    let compatible = check_capabilities(&ours, &theirs, &db, our_peer_id, remote_peer_id).await;
+    HandlerTaskOutput::HandshakeFinished { compatible }
+}
+
+/// Answers the handshake of the peer that dialed us and checks its capabilities.
+pub(crate) async fn answer_handshake<const N: usize, S: Store<N>>(
+    mut stream: KamInStreamSink<Stream>,
+    theirs: Capabilities,
+    db: Arc<Db<N, S>>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) -> HandlerTaskOutput {
+    let ours = Capabilities::of::<N, S>();
+    if stream.start_send_unpin(ResponsePacket::Handshake(ours.clone())).is_err() || stream.flush().await.is_err() {
+        warn!("{our_peer_id} Couldn't answer the handshake of {remote_peer_id}");
+        return HandlerTaskOutput::HandshakeFinished { compatible: false };
+    }
+
+    // Lets the dialer read our capabilities before the connection is closed
+    if ours.check(&theirs).is_err() {
+        let _ = timeout(HANDSHAKE_TIMEOUT, stream.next()).await;
+    }
+
+    let compatible = check_capabilities(&ours, &theirs, &db, our_peer_id, remote_peer_id).await;
+    HandlerTaskOutput::HandshakeFinished { compatible }
+}
+
+/// Closes the connection if the remote peer can't understand us.
+/// Returns true if it can.
+async fn check_capabilities<const N: usize, S: Store<N>>(
+    ours: &Capabilities,
+    theirs: &Capabilities,
+    db: &Db<N, S>,
+    our_peer_id: PeerId,
+    remote_peer_id: PeerId
+) -> bool {
+    let reason = match ours.check(theirs) {
+        Ok(()) => {
+            debug!("{our_peer_id} {remote_peer_id} is compatible (features: {:?})", theirs.features);
+            return true;
+        },
+        Err(reason) => reason,
+    };
+
+    warn!("{our_peer_id} {remote_peer_id} is incompatible: {reason}");
+    db.ban_peer(remote_peer_id, Some(INCOMPATIBLE_PEER_BAN_DURATION));
+    db.behaviour_controller().emit_event(KamilataEvent::IncompatiblePeer {
+        peer_id: remote_peer_id,
+        reason,
+    }).await;
+    db.behaviour_controller().close_connection(remote_peer_id).await;
+    false
+}
+
+pub(crate) fn handshake_boxed<const N: usize, S: Store<N>>(stream: KamOutStreamSink<Stream>, vals: PendingTaskParams) -> Pin<Box<dyn Future<Output = HandlerTaskOutput> + Send>> {
+    let vals: Box<(Arc<Db<N, S>>, PeerId, PeerId)> = vals.downcast().unwrap(); // TODO: downcast unchecked?
+    handshake(stream, vals.0, vals.1, vals.2).boxed()
+}
+
+pub(crate) fn pending_handshake<const N: usize, S: Store<N>>(db: Arc<Db<N, S>>, our_peer_id: PeerId, remote_peer_id: PeerId) -> PendingHandlerTask<PendingTaskParams> {
+    PendingHandlerTask {
+        params: Box::new((db, our_peer_id, remote_peer_id)),
+        fut: handshake_boxed::<N, S>,
+        name: "handshake",
+    }
+}
@@ -4,20 +4,32 @@ mod filter_leecher;
 mod search;
 mod request_maker;
 mod search_request;
+mod disconnect;
+mod handshake;
+mod topology;
 
-pub(self) use crate::prelude::*;
+use crate::prelude::*;
 pub(crate) use filter_seeder::*;
 pub(crate) use request_handler::*;
 pub(crate) use filter_leecher::*;
 pub(crate) use search::*;
 pub(crate) use request_maker::*;
 pub(crate) use search_request::*;
+pub(crate) use disconnect::*;
+pub(crate) use handshake::*;
+pub(crate) use topology::*;
 
 pub struct HandlerTask {
     pub fut: BoxFuture<'static, HandlerTaskOutput>,
     pub name: &'static str,
 }
 
+/// Type-erased parameters of a [PendingHandlerTask], downcasted by the task itself
+pub type PendingTaskParams = Box<dyn Any + Send>;
+
+/// A [PendingHandlerTask] along with the task id it should be assigned (see [HandlerTaskOutput::NewPendingTask])
+pub type PendingTaskInfo = (Option<(u32, bool)>, PendingHandlerTask<PendingTaskParams>);
+
 /// Task owned and ran by an [handler](ConnectionHandler)
 pub struct PendingHandlerTask<T> {
     pub params: T,
@@ -37,8 +49,10 @@ pub enum HandlerTaskOutput {
     NewPendingTask {
         /// The task id to be assigned to the task and whether this new task should replace the old one or should be dropped if the old one is still running
         tid: Option<(u32, bool)>,
-        pending_task: PendingHandlerTask<Box<dyn Any + Send>>,
+        pending_task: PendingHandlerTask<PendingTaskParams>,
     },
+    /// Tells the handler whether the handshake succeeded, see [HandshakeState]
+    HandshakeFinished { compatible: bool },
     Many(Vec<HandlerTaskOutput>),
 }
 
@@ -49,6 +63,7 @@ impl HandlerTaskOutput {
             HandlerTaskOutput::Disconnect(_) => vec![self],
             HandlerTaskOutput::SetTask {..} => vec![self],
             HandlerTaskOutput::NewPendingTask {..} => vec![self],
+            HandlerTaskOutput::HandshakeFinished {..} => vec![self],
             HandlerTaskOutput::Many(outputs) => outputs,
         }
     }
@@ -5,6 +5,7 @@ use super::*;
 pub(crate) async fn handle_request<const N: usize, S: Store<N>>(
     mut stream: KamInStreamSink<Stream>,
     db: Arc<Db<N, S>>,
+    handshake: WatchReceiver<HandshakeState>,
     our_peer_id: PeerId,
     remote_peer_id: PeerId
 ) -> HandlerTaskOutput {
@@ -18,6 +19,13 @@ pub(crate) async fn handle_request<const N: usize, S: Store<N>>(
     };
 
     debug!("{our_peer_id} Request from {remote_peer_id}: {request:?}");
+
+    // Only answer requests relying on capabilities once we know the peer understands us
+    if matches!(request, RequestPacket::GetFilters(_) | RequestPacket::Search(_)) && !wait_for_handshake(handshake).await {
+        warn!("{our_peer_id} Refusing request from {remote_peer_id} as the handshake didn't succeed");
+        let _ = stream.close().await;
+        return HandlerTaskOutput::None;
+    }
     match request {
         RequestPacket::GetFilters(refresh_packet) => {
             let task = seed_filters(stream, refresh_packet, db, our_peer_id, remote_peer_id);
@@ -61,8 +69,21 @@ pub(crate) async fn handle_request<const N: usize, S: Store<N>>(
                 }
             });
 
-            // Send results
-            while let Some(result) = receiver.recv().await {
+            // Send results, unless the peer doesn't want them anymore
+            // Dropping the receiver stops the search in the store
+            loop {
+                let result = match futures::future::select(Box::pin(receiver.recv()), stream.next()).await {
+                    future::Either::Left((Some(result), _)) => result,
+                    future::Either::Left((None, _)) => break,
+                    future::Either::Right((Some(Ok(RequestPacket::CancelSearch)), _)) => {
+                        debug!("{our_peer_id} {remote_peer_id} cancelled its search");
+                        return HandlerTaskOutput::None;
+                    },
+                    future::Either::Right((packet, _)) => {
+                        warn!("{our_peer_id} Unexpected packet from {remote_peer_id} during search: {packet:?}");
+                        return HandlerTaskOutput::None;
+                    },
+                };
                 let Ok(()) = stream.start_send_unpin(ResponsePacket::Result(ResultPacket(result.into_bytes()))) else {break};
                 let Ok(()) = stream.flush().await else {break};
             }
@@ -73,6 +94,16 @@ pub(crate) async fn handle_request<const N: usize, S: Store<N>>(
 
             HandlerTaskOutput::None
         },
-        RequestPacket::Disconnect(_) => todo!(),
+        RequestPacket::Disconnect(disconnect_packet) => {
+            handle_disconnect_packet(disconnect_packet, &db, our_peer_id, remote_peer_id).await;
+            // Lets the remote peer know the packet was received
+            let _ = stream.close().await;
+            HandlerTaskOutput::None
+        },
+        RequestPacket::CancelSearch => {
+            warn!("{our_peer_id} {remote_peer_id} cancelled a search that isn't running");
+            HandlerTaskOutput::None
+        },
+        RequestPacket::Handshake(capabilities) => answer_handshake(stream, capabilities, db, our_peer_id, remote_peer_id).await,
     }
 }
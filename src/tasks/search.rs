@@ -165,7 +165,7 @@ async fn search_one<const N: usize, S: Store<N>>(
     addresses: Vec<Multiaddr>,
     our_peer_id: PeerId,
     remote_peer_id: PeerId,
-) -> Option<(PeerId, Vec<ProviderInfo<ANY>>)> {
+) -> Option<(PeerId, Vec<ProviderInfo<ANY>>, usize)> {
     debug!("{our_peer_id} Querying {remote_peer_id} for results");
 
     // Dial the peer, orders the handle to request it, and wait for the response
@@ -173,7 +173,7 @@ async fn search_one<const N: usize, S: Store<N>>(
     let (routes_sender, mut routes_receiver) = channel(100);
     behaviour_controller.dial_peer_and_message(remote_peer_id, addresses, BehaviorToHandlerEvent::SearchRequest { query, routes_sender, result_sender: search_follower, over_notifier }).await;
     
-    let Some(routes) = routes_receiver.recv().await else {return None};
+    let routes = routes_receiver.recv().await?;
     let routes = routes.into_iter().map(|distant_match|
         ProviderInfo {
             peer_id: distant_match.peer_id.into(),
@@ -182,9 +182,9 @@ async fn search_one<const N: usize, S: Store<N>>(
         }
     ).collect::<Vec<_>>();
 
-    let _ = over_receiver.await;
+    let result_count = over_receiver.await.unwrap_or(0);
 
-    Some((remote_peer_id, routes))
+    Some((remote_peer_id, routes, result_count))
 }
  
 pub(crate) async fn search<const N: usize, S: Store<N>>(
@@ -194,57 +194,102 @@ pub(crate) async fn search<const N: usize, S: Store<N>>(
     our_peer_id: PeerId,
 ) -> TaskOutput {
     info!("{our_peer_id} Starting search task");
+    let start = Instant::now();
     let query = search_follower.query().await;
 
     // Query ourselves
     let db2 = Arc::clone(&db);
     let query2 = Arc::clone(&query);
     let search_follower2 = search_follower.clone();
-    spawn(async move {
+    let local_search = spawn(async move {
         let fut = db2.store().search(query2);
         let mut stream = fut.await;
         while let Some(result) = stream.next().await {
-            let _ = search_follower2.send((result, our_peer_id)).await;
+            if search_follower2.send((result, our_peer_id)).await.is_err() {
+                break;
+            }
         }
     });
     let routes = db.search_routes(&query).await;
     let mut config;
     let mut providers = ProviderBinaryHeap::Speed(BinaryHeap::new());
     let mut already_queried = HashSet::new();
+    let mut documents_found = 0;
+    let mut final_peers = 0;
+    // The seeder whose routes led to each provider, and seeders whose routes produced results
+    let mut origins = HashMap::new();
+    let mut useful_seeders = HashSet::new();
     for (peer_id, queries) in routes {
+        if db.is_banned(&peer_id) {
+            continue;
+        }
+        origins.insert(peer_id, peer_id);
         providers.push((peer_id, queries, Vec::new()));
     }
 
     // Keep querying new peers for new results
     let mut ongoing_requests = Vec::new();
-    loop {
-        search_follower.set_query_counts(already_queried.len(), 0, ongoing_requests.len()).await; // TODO: value instead of 0
+    let reason = loop {
+        search_follower.set_query_counts(already_queried.len(), final_peers, ongoing_requests.len()).await;
         config = search_follower.config().await;
-        providers.update_priority(config.priority, 0); // TODO: value instead of 0
+        providers.update_priority(config.priority, documents_found);
 
         // TODO: update query if needed
 
+        // Stop as soon as we are cancelled, run out of time or have enough results, dropping ongoing requests cancels them
+        if search_follower.is_cancelled() {
+            break SearchFinishReason::Cancelled;
+        }
+        if config.deadline.is_some_and(|deadline| start.elapsed() >= deadline) {
+            break SearchFinishReason::DeadlineReached;
+        }
+        let results_received = search_follower.results_received().await;
+        if config.max_results.is_some_and(|max_results| results_received >= max_results) {
+            break SearchFinishReason::ResultLimitReached;
+        }
+
         // Start new requests until limit is reached
         while ongoing_requests.len() < config.req_limit {
+            if config.max_peers.is_some_and(|max_peers| already_queried.len() >= max_peers) {
+                break;
+            }
             let Some(provider) = providers.pop() else {break};
             already_queried.insert(provider.peer_id);
             let search = search_one::<N,S>(Arc::clone(&query), behaviour_controller.clone(), search_follower.clone(), provider.addresses, our_peer_id, provider.peer_id);
-            ongoing_requests.push(Box::pin(timeout(Duration::from_millis(config.timeout_ms as u64), search)));
+            let timeout_duration = Duration::from_millis(config.timeout_ms as u64);
+            let peer_id = provider.peer_id;
+            ongoing_requests.push(Box::pin(async move { (peer_id, timeout(timeout_duration, search).await) }));
+            search_follower.send_event(SearchEvent::PeerQueried(peer_id));
         }
 
         // Ends the loop when no more requests can be made
-        if providers.is_empty() && ongoing_requests.is_empty() {
-            break;
+        if ongoing_requests.is_empty() {
+            match providers.is_empty() {
+                true => break SearchFinishReason::ProvidersExhausted,
+                false => break SearchFinishReason::PeerLimitReached,
+            }
         }
 
-        // Wait for one of the ongoing requests to finish
-        let (r, _, remaining_requests) = futures::future::select_all(ongoing_requests).await;
+        // Wait for one of the ongoing requests to finish, unless the deadline is reached or the search is cancelled
+        let next_request = futures::future::select_all(ongoing_requests);
+        let next_request = async {
+            match config.deadline {
+                Some(deadline) => timeout(deadline.saturating_sub(start.elapsed()), next_request).await.map_err(|_| SearchFinishReason::DeadlineReached),
+                None => Ok(next_request.await),
+            }
+        };
+        let (r, _, remaining_requests) = match futures::future::select(Box::pin(next_request), Box::pin(search_follower.cancelled())).await {
+            future::Either::Left((Ok(r), _)) => r,
+            future::Either::Left((Err(reason), _)) => break reason,
+            future::Either::Right(_) => break SearchFinishReason::Cancelled,
+        };
         ongoing_requests = remaining_requests;
-        let (_peer_id, routes) = match r {
-            Ok(Some(r)) => r,
-            Ok(None) => continue,
-            Err(_) => {
-                warn!("{our_peer_id} Search request timed out");
+        let (peer_id, routes, result_count) = match r {
+            (_, Ok(Some(r))) => r,
+            (_, Ok(None)) => continue,
+            (peer_id, Err(_)) => {
+                warn!("{our_peer_id} Search request to {peer_id} timed out");
+                search_follower.send_event(SearchEvent::PeerTimedOut(peer_id));
                 continue
             },
         };
@@ -252,13 +297,36 @@ pub(crate) async fn search<const N: usize, S: Store<N>>(
             warn!("{our_peer_id} Search interrupted due to results being dropped");
             return TaskOutput::None;
         }
+        documents_found += result_count;
+        let origin = origins.get(&peer_id).copied();
+        if result_count > 0 {
+            final_peers += 1;
+            useful_seeders.extend(origin);
+        }
+        let mut new_routes = 0;
         for route in routes {
-            if !already_queried.contains(&route.peer_id) && !route.addresses.is_empty() {
+            if !already_queried.contains(&route.peer_id) && !route.addresses.is_empty() && !db.is_blocked(&route.peer_id) && !db.is_banned(&route.peer_id) {
+                if let Some(origin) = origin {
+                    origins.entry(route.peer_id).or_insert(origin);
+                }
                 providers.push(route);
+                new_routes += 1;
             }
         }
-    }
+        if new_routes > 0 {
+            search_follower.send_event(SearchEvent::RoutesDiscovered(new_routes));
+        }
+    };
 
+    // Local results must be delivered before the controller is told the search is over
+    match reason {
+        SearchFinishReason::DeadlineReached | SearchFinishReason::Cancelled => local_search.abort(),
+        _ => { let _ = local_search.await; },
+    }
+    search_follower.set_query_counts(already_queried.len(), final_peers, 0).await;
+    db.record_search_hits(useful_seeders).await;
+    debug!("{our_peer_id} Search finished: {reason:?}");
+    search_follower.finish(reason).await;
     info!("{our_peer_id} Search task finished");
     
     TaskOutput::None
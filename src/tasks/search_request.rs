@@ -7,7 +7,7 @@ pub(crate) async fn search_req<const N: usize, S: Store<N>>(
     query: Arc<S::Query>,
     routes_sender: Sender<Vec<Route>>,
     result_sender: OngoingSearchFollower<N, S>,
-    over_notifier: OneshotSender<()>,
+    mut over_notifier: OneshotSender<usize>,
     our_peer_id: PeerId,
     remote_peer_id: PeerId
 ) -> HandlerTaskOutput {
@@ -34,15 +34,21 @@ pub(crate) async fn search_req<const N: usize, S: Store<N>>(
     debug!("{our_peer_id} Received {} routes from {remote_peer_id}", routes.len());
     let Ok(()) = routes_sender.send(routes).await else {return HandlerTaskOutput::None};
 
-    // Get results
+    // Get results until the search task stops waiting for us (timeout, deadline or enough results)
     let mut result_count = 0;
-    let start = Instant::now();
     loop {
-        if start.elapsed() > Duration::from_secs(20) {
-            warn!("{our_peer_id} Search takes too long {remote_peer_id}");
-            break;
-        }
-        match stream.next().await {
+        let packet = match futures::future::select(stream.next(), Box::pin(over_notifier.closed())).await {
+            future::Either::Left((packet, _)) => packet,
+            future::Either::Right(_) => {
+                debug!("{our_peer_id} Search request to {remote_peer_id} was cancelled");
+                if stream.start_send_unpin(RequestPacket::CancelSearch).is_ok() {
+                    let _ = stream.flush().await;
+                }
+                let _ = stream.close().await;
+                break;
+            },
+        };
+        match packet {
             Some(Ok(ResponsePacket::Result(ResultPacket(result)))) => {
                 match S::Result::from_bytes(&result) {
                     Ok(result) => {
@@ -67,15 +73,17 @@ pub(crate) async fn search_req<const N: usize, S: Store<N>>(
 
     debug!("{our_peer_id} Received {} results from {remote_peer_id}", result_count);
     
-    let _ = over_notifier.send(());
+    let _ = over_notifier.send(result_count);
     HandlerTaskOutput::None
 }
 
+type SearchReqParams<const N: usize, S> = (Arc<<S as Store<N>>::Query>, Sender<Vec<Route>>, OngoingSearchFollower<N, S>, OneshotSender<usize>, PeerId, PeerId);
+
 pub(crate) fn search_req_boxed<const N: usize, S: Store<N>>(
     stream: KamOutStreamSink<Stream>,
-    vals: Box<dyn Any + Send>
+    vals: PendingTaskParams
 ) -> Pin<Box<dyn Future<Output = HandlerTaskOutput> + Send>> {
-    let vals: Box<(Arc<S::Query>, Sender<Vec<Route>>, OngoingSearchFollower<N, S>, OneshotSender<()>, PeerId, PeerId)> = vals.downcast().unwrap(); // TODO: downcast unchecked?
+    let vals: Box<SearchReqParams<N, S>> = vals.downcast().unwrap(); // TODO: downcast unchecked?
     search_req::<N, S>(stream, vals.0, vals.1, vals.2, vals.3, vals.4, vals.5).boxed()
 }
 
@@ -83,10 +91,10 @@ pub(crate) fn pending_search_req<const N: usize, S: Store<N>>(
     query: Arc<S::Query>,
     routes_sender: Sender<Vec<Route>>,
     result_sender: OngoingSearchFollower<N, S>,
-    over_notifier: OneshotSender<()>,
+    over_notifier: OneshotSender<usize>,
     our_peer_id: PeerId,
     remote_peer_id: PeerId
-) -> PendingHandlerTask<Box<dyn Any + Send>> {
+) -> PendingHandlerTask<PendingTaskParams> {
     PendingHandlerTask {
         params: Box::new((query, routes_sender, result_sender, over_notifier, our_peer_id, remote_peer_id)),
         fut: search_req_boxed::<N, S>,
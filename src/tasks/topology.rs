@@ -0,0 +1,116 @@
+//! This module contains the behaviour tasks choosing which peers we leech from, when [KamilataConfig::auto_leech] is set.
+
+use super::*;
+
+/// Starts leeching from a newly connected peer if a seeder slot is free.
+pub(crate) async fn auto_leech_from<const N: usize, S: Store<N>>(db: Arc<Db<N, S>>, our_peer_id: PeerId, remote_peer_id: PeerId) -> TaskOutput {
+    if db.seeder_count().await >= db.get_config().max_seeders {
+        trace!("{our_peer_id} No free seeder slot for {remote_peer_id}");
+        return TaskOutput::None;
+    }
+    if db.is_blocked(&remote_peer_id) || db.is_banned(&remote_peer_id) || db.is_leeching_postponed(&remote_peer_id) {
+        return TaskOutput::None;
+    }
+
+    debug!("{our_peer_id} Automatically leeching from {remote_peer_id}");
+    db.behaviour_controller().dial_peer_and_message(remote_peer_id, Vec::new(), BehaviorToHandlerEvent::LeechFilters).await;
+    TaskOutput::None
+}
+
+/// Fills free seeder slots with connected peers chosen by the [SeederSelectionPolicy].
+pub(crate) async fn fill_seeder_slots<const N: usize, S: Store<N>>(db: Arc<Db<N, S>>, our_peer_id: PeerId) -> TaskOutput {
+    let config = db.get_config();
+    let Some(policy) = &config.auto_leech else {
+        return TaskOutput::None;
+    };
+    let free_slots = config.max_seeders.saturating_sub(db.seeder_count().await);
+    if free_slots == 0 {
+        return TaskOutput::None;
+    }
+
+    let candidates = db.seeder_candidates().await;
+    for peer_id in policy.select(candidates, free_slots) {
+        debug!("{our_peer_id} Replacing a lost seeder with {peer_id}");
+        db.behaviour_controller().dial_peer_and_message(peer_id, Vec::new(), BehaviorToHandlerEvent::LeechFilters).await;
+    }
+    TaskOutput::None
+}
+
+/// How long an evicted seeder has to release its spot, and a candidate to take it over, before the rotation gives up
+const HANDOVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Periodically evicts the lowest scoring seeder when a better candidate is connected, as described in [SeederRotation].
+/// The freed spot is claimed for that candidate, so that [fill_seeder_slots] can't give it to another peer.
+pub(crate) async fn rotate_seeders<const N: usize, S: Store<N>>(db: Arc<Db<N, S>>, our_peer_id: PeerId) -> TaskOutput {
+    let config = db.get_config();
+    let (Some(rotation), Some(policy)) = (&config.seeder_rotation, &config.auto_leech) else {
+        return TaskOutput::None;
+    };
+
+    // Number of consecutive rounds each seeder scored 0
+    let mut idle_rounds: HashMap<PeerId, usize> = HashMap::new();
+    loop {
+        sleep(rotation.interval).await;
+
+        let scores = db.score_seeders(rotation.search_hit_value).await;
+        idle_rounds.retain(|peer_id, _| scores.iter().any(|(seeder, _, _)| seeder == peer_id));
+        for (seeder, score, _) in &scores {
+            match *score == 0.0 {
+                true => *idle_rounds.entry(*seeder).or_default() += 1,
+                false => { idle_rounds.remove(seeder); },
+            }
+        }
+
+        // Only rotate when no slot is free, as free slots are filled without evicting anyone
+        if scores.len() < config.max_seeders {
+            continue;
+        }
+        let lowest = scores
+            .into_iter()
+            .filter(|(_, _, age)| *age >= rotation.min_seeder_age)
+            .min_by(|(_, score1, _), (_, score2, _)| score1.total_cmp(score2));
+        let Some((seeder, score, _)) = lowest else {
+            continue;
+        };
+
+        let candidates = db.seeder_candidates().await;
+        let Some(candidate) = policy.select(candidates.clone(), 1).into_iter().next() else {
+            continue;
+        };
+        let Some(candidate) = candidates.into_iter().find(|c| c.peer_id == candidate) else {
+            continue;
+        };
+        if !rotation.should_replace(score, idle_rounds.get(&seeder).copied().unwrap_or(0), &candidate) {
+            trace!("{our_peer_id} Keeping {seeder} (score: {score}) over {} (unique bits: {:?})", candidate.peer_id, candidate.unique_bits);
+            continue;
+        }
+
+        debug!("{our_peer_id} Evicting seeder {seeder} (score: {score}) in favour of {} (unique bits: {:?})", candidate.peer_id, candidate.unique_bits);
+        db.behaviour_controller().dial_peer_and_message(seeder, Vec::new(), BehaviorToHandlerEvent::StopLeeching).await;
+        idle_rounds.remove(&seeder);
+
+        // Claims the spot as soon as the evicted seeder releases it
+        let spot = timeout(HANDOVER_TIMEOUT, async {
+            loop {
+                if let Ok(spot) = db.add_seeder(candidate.peer_id).await {
+                    break spot;
+                }
+                sleep(Duration::from_millis(100)).await;
+            }
+        }).await;
+        let Ok(spot) = spot else {
+            warn!("{our_peer_id} {seeder} didn't release its seeder spot in time");
+            continue;
+        };
+        db.behaviour_controller().dial_peer_and_message(candidate.peer_id, Vec::new(), BehaviorToHandlerEvent::LeechFilters).await;
+
+        // The task leeching from the candidate takes the spot over, unless it couldn't start
+        let db = Arc::clone(&db);
+        spawn(async move {
+            sleep(HANDOVER_TIMEOUT).await;
+            drop(spot);
+            db.remove_seeder(&candidate.peer_id).await;
+            fill_seeder_slots(db, our_peer_id).await;
+        });
+    }
+}
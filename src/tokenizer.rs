@@ -0,0 +1,189 @@
+//! Splitting text into normalized words.
+//!
+//! Documents and queries must be tokenized the same way, or their words will never match.
+//! Stores declare their tokenizer in [Store::TOKENIZER](crate::store::Store::TOKENIZER).
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Describes how a [Tokenizer] splits text.
+/// Two tokenizers with the same identity must produce the same words for the same text.
+#[derive(protocol::Protocol, Debug, Clone, PartialEq, Eq)]
+pub struct TokenizerIdentity {
+    /// Name of the tokenizing algorithm
+    pub scheme: String,
+    /// Settings changing the words produced, such as the stemming language
+    pub settings: Vec<String>,
+}
+
+/// Splits text into the words that are added to filters and looked up by queries.
+pub trait Tokenizer: Send + Sync {
+    /// Splits text into normalized words.
+    /// Words that shouldn't be indexed, such as stop words, are omitted.
+    fn tokenize(&self, text: &str) -> Vec<String>;
+
+    /// Returns the identity advertised to peers.
+    fn identity(&self) -> TokenizerIdentity;
+}
+
+/// Languages with built-in stop words and stemming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    /// Returns the most common words of the language, lowercased and without diacritics.
+    pub const fn stop_words(self) -> &'static [&'static str] {
+        match self {
+            Language::English => &[
+                "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have", "he", "her", "his", "i", "in", "is", "it", "its",
+                "of", "on", "or", "she", "that", "the", "their", "they", "this", "to", "was", "were", "will", "with", "you",
+            ],
+            Language::French => &[
+                "a", "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "est", "et", "il", "ils", "je", "l", "la", "le", "les",
+                "leur", "mais", "ne", "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui", "sa", "se", "son", "sur", "un", "une", "vous",
+            ],
+            Language::German => &[
+                "auf", "aus", "bei", "das", "dass", "dem", "den", "der", "des", "die", "ein", "eine", "einen", "er", "es", "fur", "ich", "im", "in",
+                "ist", "mit", "nicht", "sie", "sind", "und", "von", "wir", "zu",
+            ],
+            Language::Spanish => &[
+                "a", "al", "con", "de", "del", "el", "en", "es", "la", "las", "lo", "los", "mas", "no", "para", "por", "que", "se", "su", "sus", "un",
+                "una", "y",
+            ],
+        }
+    }
+
+    #[cfg(feature = "stemming")]
+    fn stemming_algorithm(self) -> rust_stemmers::Algorithm {
+        match self {
+            Language::English => rust_stemmers::Algorithm::English,
+            Language::French => rust_stemmers::Algorithm::French,
+            Language::German => rust_stemmers::Algorithm::German,
+            Language::Spanish => rust_stemmers::Algorithm::Spanish,
+        }
+    }
+}
+
+/// A [Tokenizer] splitting text on anything that isn't a letter or a digit.
+///
+/// Words are lowercased and stripped of their diacritics, so that `Café` and `cafe` match.
+/// Stop words and stemming are disabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnicodeTokenizer {
+    min_len: usize,
+    stop_words: &'static [&'static str],
+    #[cfg(feature = "stemming")]
+    stemming: Option<Language>,
+}
+
+impl UnicodeTokenizer {
+    /// The tokenizer used by [Store::TOKENIZER](crate::store::Store::TOKENIZER) unless the store overrides it.
+    pub const DEFAULT: UnicodeTokenizer = UnicodeTokenizer::new();
+
+    pub const fn new() -> UnicodeTokenizer {
+        UnicodeTokenizer {
+            min_len: 1,
+            stop_words: &[],
+            #[cfg(feature = "stemming")]
+            stemming: None,
+        }
+    }
+
+    /// Omits words shorter than `min_len` characters.
+    pub const fn with_min_len(self, min_len: usize) -> UnicodeTokenizer {
+        UnicodeTokenizer { min_len, ..self }
+    }
+
+    /// Omits these words, which must be lowercased and without diacritics.
+    pub const fn with_stop_words(self, stop_words: &'static [&'static str]) -> UnicodeTokenizer {
+        UnicodeTokenizer { stop_words, ..self }
+    }
+
+    /// Reduces words to their stem, so that `searching` and `searches` match.
+    /// 
+    /// Peers only understand each other's filters if they stem words the same way, so this is never enabled implicitly.
+    #[cfg(feature = "stemming")]
+    pub const fn with_stemming(self, language: Language) -> UnicodeTokenizer {
+        UnicodeTokenizer { stemming: Some(language), ..self }
+    }
+
+    /// Omits the stop words of the language.
+    /// Stemming has to be enabled separately with `with_stemming`, which requires the `stemming` feature.
+    pub const fn with_language(self, language: Language) -> UnicodeTokenizer {
+        self.with_stop_words(language.stop_words())
+    }
+}
+
+impl Default for UnicodeTokenizer {
+    fn default() -> Self {
+        UnicodeTokenizer::DEFAULT
+    }
+}
+
+impl Tokenizer for UnicodeTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let normalized = text.to_lowercase().nfd().filter(|c| !is_combining_mark(*c)).collect::<String>();
+        let words = normalized
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty() && word.chars().count() >= self.min_len)
+            .filter(|word| !self.stop_words.contains(word));
+
+        // The stemmer is created once for the whole text
+        #[cfg(feature = "stemming")]
+        if let Some(language) = self.stemming {
+            let stemmer = rust_stemmers::Stemmer::create(language.stemming_algorithm());
+            return words.map(|word| stemmer.stem(word).into_owned()).collect();
+        }
+        words.map(String::from).collect()
+    }
+
+    fn identity(&self) -> TokenizerIdentity {
+        #[cfg(feature = "stemming")]
+        let stemming = self.stemming;
+        #[cfg(not(feature = "stemming"))]
+        let stemming: Option<Language> = None;
+        TokenizerIdentity {
+            scheme: String::from("unicode"),
+            settings: vec![
+                format!("min_len={}", self.min_len),
+                format!("stop_words={}", self.stop_words.join(",")),
+                format!("stemming={}", stemming.map(|language| format!("{language:?}")).unwrap_or_else(|| String::from("none"))),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_tokenizer() {
+        let tokenizer = UnicodeTokenizer::new();
+        assert_eq!(tokenizer.tokenize("Crème Brûlée, l'ÉTÉ & p2p-network!"), vec!["creme", "brulee", "l", "ete", "p2p", "network"]);
+        assert!(tokenizer.tokenize("  ...  ").is_empty());
+
+        let tokenizer = UnicodeTokenizer::new().with_min_len(3).with_language(Language::English);
+        assert_eq!(tokenizer.tokenize("The art of war"), vec!["art", "war"]);
+        let tokenizer = UnicodeTokenizer::new().with_stop_words(Language::French.stop_words());
+        assert_eq!(tokenizer.tokenize("Le café de l'été"), vec!["cafe", "ete"]);
+
+        assert_eq!(UnicodeTokenizer::new().identity(), UnicodeTokenizer::DEFAULT.identity());
+        assert_ne!(UnicodeTokenizer::new().identity(), UnicodeTokenizer::new().with_min_len(3).identity());
+        assert_ne!(UnicodeTokenizer::new().identity(), tokenizer.identity());
+    }
+
+    #[cfg(feature = "stemming")]
+    #[test]
+    fn stemming() {
+        let tokenizer = UnicodeTokenizer::new().with_language(Language::English);
+        assert_eq!(tokenizer.tokenize("Searching searches"), vec!["searching", "searches"]);
+        let stemming_tokenizer = tokenizer.with_stemming(Language::English);
+        assert_eq!(stemming_tokenizer.tokenize("Searching searches"), vec!["search", "search"]);
+        assert_ne!(stemming_tokenizer.identity(), tokenizer.identity());
+    }
+}
@@ -0,0 +1,185 @@
+//! Automatic selection of the peers we leech from, and prioritization of the peers leeching from us.
+//!
+//! This is opt-in, see [KamilataConfig::auto_leech] and [KamilataConfig::leecher_policy].
+
+use crate::prelude::*;
+use std::{cmp::Reverse, collections::hash_map::RandomState, hash::BuildHasher};
+
+/// A connected peer we could leech from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeederCandidate {
+    pub peer_id: PeerId,
+    /// Number of bits set in the level-0 filter the peer sent us the last time we leeched from it.
+    /// None if we never leeched from it.
+    pub set_bits: Option<usize>,
+    /// Among those bits, the number of bits none of our current seeders provide at level 0.
+    pub unique_bits: Option<usize>,
+}
+
+/// Chooses which peers to leech from when seeder slots are free.
+pub trait SeederSelectionPolicy: Send + Sync {
+    /// Returns up to `count` peers to leech from, taken from `candidates`.
+    fn select(&self, candidates: Vec<SeederCandidate>, count: usize) -> Vec<PeerId>;
+}
+
+/// Built-in [SeederSelectionPolicy]s.
+///
+/// Peers we never leeched from are unknown and come after the others, in random order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeederSelection {
+    /// Picks candidates at random.
+    Random,
+    /// Prefers candidates whose own filter has the most bits set, as they are expected to have the most documents.
+    MostDocuments,
+    /// Prefers candidates whose own filter adds the most bits to what our current seeders provide.
+    MostUniqueCoverage,
+}
+
+impl SeederSelectionPolicy for SeederSelection {
+    fn select(&self, mut candidates: Vec<SeederCandidate>, count: usize) -> Vec<PeerId> {
+        let random = RandomState::new();
+        candidates.sort_by_cached_key(|candidate| {
+            let score = match self {
+                SeederSelection::Random => None,
+                SeederSelection::MostDocuments => candidate.set_bits,
+                SeederSelection::MostUniqueCoverage => candidate.unique_bits,
+            };
+            (Reverse(score), random.hash_one(candidate.peer_id))
+        });
+        candidates.into_iter().take(count).map(|candidate| candidate.peer_id).collect()
+    }
+}
+
+/// Settings for periodically replacing the least useful seeder, see [KamilataConfig::seeder_rotation].
+/// 
+/// The score of a seeder is the number of bits only its level-0 filter provides, plus [SeederRotation::search_hit_value] for each search its routes produced results in.
+/// Search hits are halved after each round so that old searches matter less.
+#[derive(Debug, Clone)]
+pub struct SeederRotation {
+    /// How often seeders are scored (default: 5 minutes)
+    pub interval: Duration,
+    /// Seeders we leeched from for less than this are never evicted (default: 10 minutes)
+    pub min_seeder_age: Duration,
+    /// Score given to a seeder for each search in which its routes produced results (default: 100)
+    pub search_hit_value: f64,
+    /// A candidate replaces the lowest scoring seeder only if the bits it would add are worth more than this many times the seeder's score (default: 1.5)
+    /// 
+    /// Candidates we never leeched from are unknown and only replace seeders with a score of 0.
+    pub replacement_margin: f64,
+    /// Number of consecutive rounds a seeder must score 0 before a candidate we never leeched from replaces it (default: 3)
+    /// 
+    /// This keeps unknown candidates, which often turn out to be useless too, from replacing each other at every round.
+    pub unknown_candidate_rounds: usize,
+}
+
+impl Default for SeederRotation {
+    fn default() -> Self {
+        SeederRotation {
+            interval: Duration::from_secs(5 * 60),
+            min_seeder_age: Duration::from_secs(10 * 60),
+            search_hit_value: 100.0,
+            replacement_margin: 1.5,
+            unknown_candidate_rounds: 3,
+        }
+    }
+}
+
+impl SeederRotation {
+    /// Returns true if the seeder should be evicted in favour of the candidate.
+    /// `idle_rounds` is the number of consecutive rounds, including this one, in which the seeder scored 0.
+    pub(crate) fn should_replace(&self, seeder_score: f64, idle_rounds: usize, candidate: &SeederCandidate) -> bool {
+        match candidate.unique_bits {
+            Some(unique_bits) => unique_bits as f64 > seeder_score * self.replacement_margin,
+            None => seeder_score == 0.0 && idle_rounds >= self.unknown_candidate_rounds,
+        }
+    }
+}
+
+/// Why a peer refused to seed to us, see [KamilataEvent::LeechRejected].
+#[derive(protocol::Protocol, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// All its leecher spots are taken by peers with the same priority or a higher one.
+    TooManyLeechers,
+    /// Its [KamilataConfig::approve_leecher] closure refused us.
+    NotApproved,
+    /// Our [KamilataConfig::get_filters_interval] doesn't overlap with its own.
+    IntervalMismatch,
+    /// It blocked us.
+    Blocked,
+    /// It hashes words differently, so its filters would be meaningless to us.
+    HasherMismatch,
+    /// Its [LeecherPolicy] gave our spot to a peer with a higher priority.
+    Preempted,
+}
+
+impl std::fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectionReason::TooManyLeechers => write!(f, "too many leechers"),
+            RejectionReason::NotApproved => write!(f, "not approved"),
+            RejectionReason::IntervalMismatch => write!(f, "couldn't agree on an update interval"),
+            RejectionReason::Blocked => write!(f, "blocked"),
+            RejectionReason::HasherMismatch => write!(f, "words are hashed differently"),
+            RejectionReason::Preempted => write!(f, "preempted by a leecher with a higher priority"),
+        }
+    }
+}
+
+/// How long a leecher preempted by a [LeecherPolicy] is asked to wait before leeching from us again, unless the policy overrides it
+pub const DEFAULT_PREEMPTED_LEECHER_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Prioritizes the peers asking for our filters.
+///
+/// When all leecher spots are taken, a peer with a higher priority than one of our leechers takes the spot of the leecher with the lowest priority.
+/// Peers with the same priority never preempt each other.
+pub trait LeecherPolicy: Send + Sync {
+    /// Returns the priority of a peer asking for our filters.
+    /// Higher values win.
+    fn priority(&self, peer_id: PeerId) -> u32;
+
+    /// Returns how long a preempted leecher should wait before leeching from us again.
+    /// None means it should never come back.
+    fn retry_delay(&self) -> Option<Duration> {
+        Some(DEFAULT_PREEMPTED_LEECHER_RETRY_DELAY)
+    }
+}
+
+impl<F: Fn(PeerId) -> u32 + Send + Sync> LeecherPolicy for F {
+    fn priority(&self, peer_id: PeerId) -> u32 {
+        self(peer_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeder_selection() {
+        let candidate = |set_bits, unique_bits| SeederCandidate { peer_id: PeerId::random(), set_bits, unique_bits };
+        let candidates = vec![candidate(Some(100), Some(5)), candidate(None, None), candidate(Some(50), Some(50)), candidate(Some(10), Some(0))];
+        let peer_ids = candidates.iter().map(|c| c.peer_id).collect::<Vec<_>>();
+
+        assert_eq!(SeederSelection::MostDocuments.select(candidates.clone(), 2), vec![peer_ids[0], peer_ids[2]]);
+        assert_eq!(SeederSelection::MostUniqueCoverage.select(candidates.clone(), 3), vec![peer_ids[2], peer_ids[0], peer_ids[3]]);
+        assert_eq!(SeederSelection::MostDocuments.select(candidates.clone(), 10)[3], peer_ids[1]);
+
+        let selected = SeederSelection::Random.select(candidates.clone(), 2);
+        assert_eq!(selected.len(), 2);
+        assert!(selected.iter().all(|peer_id| peer_ids.contains(peer_id)));
+        assert!(SeederSelection::Random.select(candidates, 0).is_empty());
+    }
+
+    #[test]
+    fn seeder_rotation() {
+        let rotation = SeederRotation::default();
+        let candidate = |unique_bits| SeederCandidate { peer_id: PeerId::random(), set_bits: unique_bits, unique_bits };
+        assert!(rotation.should_replace(100.0, 0, &candidate(Some(151))));
+        assert!(!rotation.should_replace(100.0, 0, &candidate(Some(150))));
+        assert!(rotation.should_replace(0.0, 1, &candidate(Some(1))));
+        assert!(!rotation.should_replace(0.0, 1, &candidate(Some(0))));
+        assert!(!rotation.should_replace(0.0, 2, &candidate(None)));
+        assert!(rotation.should_replace(0.0, 3, &candidate(None)));
+        assert!(!rotation.should_replace(1.0, 0, &candidate(None)));
+    }
+}
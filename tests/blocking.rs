@@ -0,0 +1,72 @@
+//! Tests making sure leechers only receive the filters they asked for.
+//! In both tests, client 1 leeches from client 2 which leeches from client 3, the only one having a document.
+
+mod common;
+use common::*;
+
+fn node_config(filter_count: usize) -> KamilataConfig {
+    KamilataConfig {
+        get_filters_interval: MinTargetMax::new(1000, 1000, 1000),
+        filter_count,
+        ..KamilataConfig::default()
+    }
+}
+
+async fn init_network(filter_count: usize) -> (Movie, ClientController, ClientController, ClientController) {
+    let doc = Movie::perfect_match(0);
+
+    let mut client1 = Client::init_with_config(node_config(filter_count)).await;
+    let mut client2 = Client::init_with_config(node_config(filter_count)).await;
+    let client3 = Client::init_with_config(node_config(filter_count)).await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client2.swarm_mut().dial(DialOpts::peer_id(client3.peer_id()).addresses(vec![client3.addr().to_owned()]).build()).unwrap();
+    client3.store().insert_document(doc.clone()).await;
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    c2.leech_from(&c3).await;
+
+    info!("Waiting for filters to propagate...");
+    sleep(Duration::from_secs(3)).await;
+
+    (doc, c1, c2, c3)
+}
+
+#[tokio::test]
+async fn filter_count() {
+    let (_doc, c1, _c2, _c3) = init_network(1).await;
+
+    // Client 1 only knows about the documents of client 2
+    let results = c1.search(["perfectly"].as_slice()).await;
+    assert!(results.hits.is_empty());
+    assert_eq!(results.queried_peers, 0);
+}
+
+#[tokio::test]
+async fn blocked_peers() {
+    let (doc, c1, _c2, c3) = init_network(8).await;
+
+    let results = c1.search(["perfectly"].as_slice()).await;
+    let hits = results.hits.into_iter().map(|h| h.0).collect::<Vec<_>>();
+    assert_eq!(hits, vec![doc]);
+
+    info!("Blocking client 3...");
+    c1.block_peer(&c3).await;
+    sleep(Duration::from_secs(1)).await;
+
+    // Client 2 now leaves client 3 out of the filters it sends us
+    let results = c1.search(["perfectly"].as_slice()).await;
+    assert!(results.hits.is_empty());
+    assert_eq!(results.queried_peers, 0);
+}
@@ -1,6 +1,6 @@
 
 use futures::future;
-use libp2p::{identity::{self, Keypair}, core::transport::MemoryTransport, PeerId, Transport, Swarm, Multiaddr, swarm::{SwarmEvent, SwarmBuilder}};
+use libp2p::{identity::{self, Keypair}, core::transport::MemoryTransport, PeerId, Transport, Swarm, Multiaddr, swarm::{SwarmEvent, Config as SwarmConfig}};
 
 use tokio::sync::{
     mpsc::*,
@@ -40,13 +40,40 @@ pub enum ClientCommand {
         sender: OneshotSender<SearchResults<Movie>>,
         config: SearchConfig,
     },
+    SearchEvents {
+        query: MovieQuery,
+        sender: OneshotSender<(Vec<SearchEvent>, SearchResults<Movie>)>,
+        config: SearchConfig,
+    },
+    SearchAndCancel {
+        query: MovieQuery,
+        sender: OneshotSender<Vec<SearchEvent>>,
+    },
     GetRoutingStats {
         sender: OneshotSender<(usize, usize)>,
     },
+    TakeEvents {
+        sender: OneshotSender<Vec<KamilataEvent>>,
+    },
+    IsConnected {
+        peer_id: PeerId,
+        sender: OneshotSender<bool>,
+    },
     LeechFrom {
         seeder: PeerId,
     },
     LeechFromAll,
+    Disconnect {
+        peer_id: PeerId,
+        try_again_in: Option<Duration>,
+    },
+    BlockPeer {
+        peer_id: PeerId,
+    },
+    InsertDocument {
+        doc: Movie,
+        notify: bool,
+    },
 }
 
 pub struct ClientController {
@@ -55,6 +82,10 @@ pub struct ClientController {
 }
 
 impl ClientController {
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
     pub async fn dial(&self, addr: Multiaddr) {
         self.sender.send(ClientCommand::Dial { addr }).await.unwrap();
     }
@@ -67,6 +98,20 @@ impl ClientController {
         self.sender.send(ClientCommand::LeechFromAll).await.unwrap();
     }
 
+    pub async fn disconnect(&self, peer: &ClientController, try_again_in: Option<Duration>) {
+        self.sender.send(ClientCommand::Disconnect { peer_id: peer.peer_id, try_again_in }).await.unwrap();
+    }
+
+    pub async fn is_connected(&self, peer: &ClientController) -> bool {
+        let (sender, receiver) = oneshot_channel();
+        self.sender.send(ClientCommand::IsConnected { peer_id: peer.peer_id, sender }).await.unwrap();
+        receiver.await.unwrap()
+    }
+
+    pub async fn block_peer(&self, peer: &ClientController) {
+        self.sender.send(ClientCommand::BlockPeer { peer_id: peer.peer_id }).await.unwrap();
+    }
+
     pub async fn search(&self, query: impl Into<MovieQuery>) -> SearchResults<Movie> {
         self.search_with_config(query, SearchConfig::default()).await
     }
@@ -85,6 +130,39 @@ impl ClientController {
         receiver.await.unwrap()
     }
 
+    /// Returns all the events of a search in order, along with its results.
+    pub async fn search_events(&self, query: impl Into<MovieQuery>, config: SearchConfig) -> (Vec<SearchEvent>, SearchResults<Movie>) {
+        let (sender, receiver) = oneshot_channel();
+        self.sender.send(ClientCommand::SearchEvents {
+            query: query.into(),
+            sender,
+            config,
+        }).await.unwrap();
+        receiver.await.unwrap()
+    }
+
+    /// Starts a search and cancels it as soon as a peer is queried, returning all its events.
+    pub async fn search_and_cancel(&self, query: impl Into<MovieQuery>) -> Vec<SearchEvent> {
+        let (sender, receiver) = oneshot_channel();
+        self.sender.send(ClientCommand::SearchAndCancel {
+            query: query.into(),
+            sender,
+        }).await.unwrap();
+        receiver.await.unwrap()
+    }
+
+    /// Inserts a document while the client is running, optionally notifying the behaviour that the store changed.
+    pub async fn insert_document(&self, doc: Movie, notify: bool) {
+        self.sender.send(ClientCommand::InsertDocument { doc, notify }).await.unwrap();
+    }
+
+    /// Returns the behaviour events produced since the last call.
+    pub async fn take_events(&self) -> Vec<KamilataEvent> {
+        let (sender, receiver) = oneshot_channel();
+        self.sender.send(ClientCommand::TakeEvents { sender }).await.unwrap();
+        receiver.await.unwrap()
+    }
+
     /// Returns (seeder_count, leecher_count)
     pub async fn get_routing_stats(&self) -> (usize, usize) {
         let (sender, receiver) = oneshot_channel();
@@ -95,6 +173,14 @@ impl ClientController {
     }
 }
 
+/// A config refreshing filters every `target_ms`, and allowing updates as often as every `min_ms`.
+pub fn refresh_config(min_ms: usize, target_ms: usize) -> KamilataConfig {
+    KamilataConfig {
+        get_filters_interval: MinTargetMax::new(min_ms, target_ms, target_ms),
+        ..KamilataConfig::default()
+    }
+}
+
 impl Client {
     pub async fn init() -> Self {
         Self::init_with_config(KamilataConfig::default()).await
@@ -113,7 +199,7 @@ impl Client {
         // can be observed.
         let behaviour = KamilataBehaviour::new_with_config(local_peer_id, config);
     
-        let mut swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, local_peer_id).build();
+        let mut swarm = Swarm::new(transport, behaviour, local_peer_id, SwarmConfig::with_tokio_executor());
     
         // Tell the swarm to listen on all interfaces and a random, OS-assigned port.
         let mut addr: Option<Multiaddr> = None;
@@ -168,6 +254,7 @@ impl Client {
     pub fn run(mut self) -> ClientController {
         let (sender, mut receiver) = channel(1);
         tokio::spawn(async move {
+            let mut behaviour_events = Vec::new();
             loop {
                 let recv = Box::pin(receiver.recv());
                 let value = futures::future::select(recv, self.swarm.select_next_some()).await;
@@ -185,12 +272,24 @@ impl Client {
                                 self.swarm.behaviour_mut().leech_from(peer_id);
                             }
                         },
+                        ClientCommand::Disconnect { peer_id, try_again_in } => {
+                            self.swarm.behaviour_mut().disconnect(peer_id, "Test disconnection", try_again_in);
+                        },
+                        ClientCommand::BlockPeer { peer_id } => {
+                            self.swarm.behaviour_mut().block_peer(peer_id);
+                        },
+                        ClientCommand::InsertDocument { doc, notify } => {
+                            self.swarm.behaviour().store().insert_document(doc).await;
+                            if notify {
+                                self.swarm.behaviour().notify_store_changed();
+                            }
+                        },
                         ClientCommand::Search { query: queries, sender, config } => {
                             let mut controler = self.swarm.behaviour_mut().search_with_config(queries, config).await;
                     
                             tokio::spawn(async move {
                                 let mut hits = Vec::new();
-                                while let Some(hit) = controler.recv().await {
+                                while let Some(hit) = controler.next().await {
                                     hits.push(hit);
                                 }
                                 let mut results = controler.finish().await;
@@ -198,15 +297,55 @@ impl Client {
                                 sender.send(results).unwrap();
                             });
                         },
+                        ClientCommand::SearchEvents { query, sender, config } => {
+                            let mut controler = self.swarm.behaviour_mut().search_with_config(query, config).await;
+
+                            tokio::spawn(async move {
+                                let mut hits = Vec::new();
+                                while let Some(hit) = controler.next().await {
+                                    hits.push(hit);
+                                }
+                                let mut events = Vec::new();
+                                while let Some(event) = controler.recv_event().await {
+                                    events.push(event);
+                                }
+                                let mut results = controler.finish().await;
+                                results.hits = hits;
+                                sender.send((events, results)).unwrap();
+                            });
+                        },
+                        ClientCommand::SearchAndCancel { query, sender } => {
+                            let mut controler = self.swarm.behaviour_mut().search(query).await;
+
+                            tokio::spawn(async move {
+                                let mut events = Vec::new();
+                                while let Some(event) = controler.recv_event().await {
+                                    if let SearchEvent::PeerQueried(_) = event {
+                                        controler.cancel();
+                                    }
+                                    events.push(event);
+                                }
+                                sender.send(events).unwrap();
+                            });
+                        },
                         ClientCommand::GetRoutingStats { sender } => {
                             let seeder_count = self.swarm.behaviour_mut().seeder_count().await;
                             let leecher_count = self.swarm.behaviour_mut().leecher_count().await;
                             sender.send((seeder_count, leecher_count)).unwrap();    
-                        }
+                        },
+                        ClientCommand::TakeEvents { sender } => {
+                            sender.send(std::mem::take(&mut behaviour_events)).unwrap();
+                        },
+                        ClientCommand::IsConnected { peer_id, sender } => {
+                            sender.send(self.swarm.is_connected(&peer_id)).unwrap();
+                        },
                     },
                     future::Either::Left((None, _)) => break,
                     future::Either::Right((event, _)) => match event {
-                        SwarmEvent::Behaviour(e) => info!("{} produced behaviour event {e:?}", self.local_peer_id),
+                        SwarmEvent::Behaviour(e) => {
+                            info!("{} produced behaviour event {e:?}", self.local_peer_id);
+                            behaviour_events.push(e);
+                        },
                         SwarmEvent::NewListenAddr { listener_id, address } => debug!("{} is listening on {address:?} (listener id: {listener_id:?})", self.local_peer_id),
                         _ => ()
                     },
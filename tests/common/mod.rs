@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![allow(dead_code, unused_imports)]
 
 mod logger;
 mod movies;
@@ -7,8 +7,7 @@ pub use logger::*;
 pub use movies::*;
 pub use client::*;
 
-pub(self) use kamilata::prelude::*;
-pub(self) use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize};
 pub use kamilata::prelude::*;
 pub use log::*;
 pub use libp2p::swarm::dial_opts::DialOpts;
@@ -10,7 +10,30 @@ pub struct Movie {
     pub release_date: i64,
 }
 
+/// Tokenizer shared by movies and their store
+const MOVIE_TOKENIZER: UnicodeTokenizer = UnicodeTokenizer::new().with_min_len(3);
+
 impl Movie {
+    /// A movie with nothing but a title, for tests that just need a few searchable words.
+    pub fn titled(id: usize, title: &str) -> Movie {
+        Movie {
+            id,
+            title: String::from(title),
+            overview: String::new(),
+            genres: Vec::new(),
+            poster: String::new(),
+            release_date: 0,
+        }
+    }
+
+    /// The movie found by tests searching for "perfectly".
+    pub fn perfect_match(id: usize) -> Movie {
+        Movie {
+            overview: String::from("This is the perfectly matching document"),
+            ..Movie::titled(id, "Perfect match")
+        }
+    }
+
     fn full_text(&self) -> String {
         let mut full_text = String::new();
         full_text.push_str(&self.title);
@@ -21,12 +44,11 @@ impl Movie {
             full_text.push_str(genre);
             full_text.push(' ');
         }
-        full_text = full_text.to_lowercase();
         full_text
     }
 
     pub fn words(&self) -> Vec<String> {
-        self.full_text().split(|c: char| c.is_whitespace() || c.is_ascii_punctuation()).filter(|w| w.len() >= 3).map(|w| w.to_string()).collect()
+        MOVIE_TOKENIZER.tokenize(&self.full_text())
     }
 }
 
@@ -85,17 +107,7 @@ impl<const N: usize> Store<N> for MovieIndex<N> {
     type Result = Movie;
     type Query = MovieQuery;
 
-    fn hash_word(word: &str) -> Vec<usize> {
-        let mut result = 1usize;
-        const RANDOM_SEED: [usize; 16] = [542587211452, 5242354514, 245421154, 4534542154, 542866467, 545245414, 7867569786914, 88797854597, 24542187316, 645785447, 434963879, 4234274, 55418648642, 69454242114688, 74539841, 454214578213];
-        for c in word.bytes() {
-            for i in 0..8 {
-                result = result.overflowing_mul(c as usize + RANDOM_SEED[i*2]).0;
-                result = result.overflowing_add(c as usize + RANDOM_SEED[i*2+1]).0;
-            }
-        }
-        vec![result % (N * 8)]
-    }
+    const TOKENIZER: &'static dyn Tokenizer = &MOVIE_TOKENIZER;
 
     async fn get_filter(&self) -> Filter<N> {
         self.inner.read().await.filter.clone()
@@ -140,14 +152,14 @@ impl<const N: usize> Store<N> for MovieIndex<N> {
 impl<const N: usize> MovieIndex<N> {
     pub async fn insert_document(&self, doc: Movie) {
         let mut inner = self.inner.write().await;
-        doc.words().iter().for_each(|w| inner.filter.add_word::<Self>(w));
+        inner.filter.add_text::<Self>(&doc.full_text());
         inner.movies.push(doc);
     }
 
     pub async fn insert_documents(&self, docs: &[Movie]) {
         let mut inner = self.inner.write().await;
         for doc in docs {
-            doc.words().iter().for_each(|w| inner.filter.add_word::<Self>(w));
+            inner.filter.add_text::<Self>(&doc.full_text());
             inner.movies.push(doc.to_owned());
         }
     }
@@ -0,0 +1,59 @@
+//! Tests making sure results replicated on several peers are only delivered once when deduplication is enabled.
+//! Client 1 leeches from clients 2 and 3, which both have the same document.
+
+mod common;
+use common::*;
+
+async fn init_network() -> (Movie, ClientController, ClientController, ClientController) {
+    let doc = Movie::perfect_match(0);
+
+    let mut client1 = Client::init().await;
+    let client2 = Client::init().await;
+    let client3 = Client::init().await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client1.swarm_mut().dial(DialOpts::peer_id(client3.peer_id()).addresses(vec![client3.addr().to_owned()]).build()).unwrap();
+    client2.store().insert_document(doc.clone()).await;
+    client3.store().insert_document(doc.clone()).await;
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    c1.leech_from(&c3).await;
+
+    info!("Waiting for filters to propagate...");
+    sleep(Duration::from_secs(1)).await;
+
+    (doc, c1, c2, c3)
+}
+
+#[tokio::test]
+async fn deduplication() {
+    let (doc, c1, c2, c3) = init_network().await;
+
+    // Without deduplication, the document is received from both providers
+    let results = c1.search(["perfectly"].as_slice()).await;
+    assert_eq!(results.hits.len(), 2);
+    assert!(results.providers.is_empty());
+    assert_eq!(results.queried_peers, 2);
+    assert_eq!(results.final_peers, 2);
+
+    // With deduplication, it is received once and both providers are listed
+    let results = c1.search_with_config(["perfectly"].as_slice(), SearchConfig::default().with_deduplication(true)).await;
+    assert_eq!(results.hits.len(), 1);
+    let hits = results.hits_with_providers();
+    let (hit, providers) = &hits[0];
+    assert_eq!(*hit, doc);
+    assert_eq!(providers.len(), 2);
+    assert!(providers.contains(&c2.peer_id()));
+    assert!(providers.contains(&c3.peer_id()));
+}
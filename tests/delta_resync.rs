@@ -0,0 +1,56 @@
+//! A test making sure seeders send deltas and leechers keep applying them after a full update.
+//! Client 1 leeches from client 2 which leeches from client 3, then client 1 blocks client 3 and gets a full update.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn delta_resync() {
+    let mut client1 = Client::init_with_config(refresh_config(500, 1_000)).await;
+    let mut client2 = Client::init_with_config(refresh_config(500, 1_000)).await;
+    let client3 = Client::init_with_config(refresh_config(500, 1_000)).await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client2.swarm_mut().dial(DialOpts::peer_id(client3.peer_id()).addresses(vec![client3.addr().to_owned()]).build()).unwrap();
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    c2.leech_from(&c3).await;
+    sleep(Duration::from_secs(1)).await;
+
+    // Documents of both seeders are advertised through deltas
+    c2.insert_document(Movie::titled(0, "Delta"), false).await;
+    c3.insert_document(Movie::titled(1, "Distant"), false).await;
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(c1.search(["delta"].as_slice()).await.hits.len(), 1);
+    assert_eq!(c1.search(["distant"].as_slice()).await.hits.len(), 1);
+
+    // Client 2 sends a full update leaving client 3 out
+    c1.block_peer(&c3).await;
+    sleep(Duration::from_secs(2)).await;
+    assert!(c1.search(["distant"].as_slice()).await.hits.is_empty());
+    assert_eq!(c1.search(["delta"].as_slice()).await.hits.len(), 1);
+
+    // Later deltas apply on top of the full update
+    c2.insert_document(Movie::titled(2, "Resync"), false).await;
+    c3.insert_document(Movie::titled(3, "Hidden"), false).await;
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(c1.search(["resync"].as_slice()).await.hits.len(), 1);
+    assert!(c1.search(["hidden"].as_slice()).await.hits.is_empty());
+    assert_eq!(c1.get_routing_stats().await, (1, 0));
+
+    // Client 2 sent consistent filters all along
+    let events = c1.take_events().await;
+    assert!(!events.iter().any(|event| matches!(event, KamilataEvent::SeederMisbehaved { .. })));
+    assert!(!events.iter().any(|event| matches!(event, KamilataEvent::SeederRemoved { .. })));
+}
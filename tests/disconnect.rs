@@ -0,0 +1,47 @@
+//! A test making sure peers that disconnected us are neither redialed nor leeched from again.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn disconnect() -> Result<(), Box<dyn std::error::Error>> {
+    let client1 = Client::init().await;
+    let client2 = Client::init().await;
+    let addr2 = client2.addr().clone();
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.activate();
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+
+    info!("Connecting...");
+    c1.dial(addr2.clone()).await;
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (1, 0));
+    assert_eq!(c2.get_routing_stats().await, (0, 1));
+    assert!(c1.is_connected(&c2).await);
+
+    info!("Disconnecting...");
+    c2.disconnect(&c1, None).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 0));
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+
+    info!("Reconnecting...");
+    c1.dial(addr2).await;
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    sleep(Duration::from_secs(1)).await;
+
+    // Client 2 asked client 1 to never come back
+    assert!(!c1.is_connected(&c2).await);
+    assert_eq!(c1.get_routing_stats().await, (0, 0));
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+
+    Ok(())
+}
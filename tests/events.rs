@@ -0,0 +1,36 @@
+//! Tests making sure search progress is reported through events.
+//! Client 1 leeches from client 2 which has a document.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn search_events() {
+    let doc = Movie::perfect_match(0);
+
+    let mut client1 = Client::init().await;
+    let client2 = Client::init().await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client2.store().insert_document(doc.clone()).await;
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    sleep(Duration::from_secs(1)).await;
+
+    let (events, results) = c1.search_events(["perfectly"].as_slice(), SearchConfig::default()).await;
+    info!("Search events: {events:?}");
+    assert_eq!(results.hits, vec![(doc, c2.peer_id())]);
+    assert!(matches!(events.first(), Some(SearchEvent::PeerQueried(peer_id)) if *peer_id == c2.peer_id()));
+    assert!(events.contains(&SearchEvent::ResultReceived(c2.peer_id())));
+    assert!(matches!(events.last(), Some(SearchEvent::Finished { reason: SearchFinishReason::ProvidersExhausted })));
+    assert_eq!(events.iter().filter(|e| matches!(e, SearchEvent::Finished { .. })).count(), 1);
+}
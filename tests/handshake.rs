@@ -0,0 +1,64 @@
+//! A test making sure peers exchange their capabilities on connection and refuse incompatible ones.
+//! Clients 1 and 2 are compatible. Client 3 uses smaller filters and dials client 1, which refuses it.
+
+mod common;
+use common::*;
+use futures::StreamExt;
+use libp2p::{identity::Keypair, swarm::{Config as SwarmConfig, SwarmEvent}, Swarm};
+use tokio::time::timeout;
+
+fn incompatibility(events: Vec<KamilataEvent>) -> Option<(libp2p::PeerId, Incompatibility)> {
+    events.into_iter().find_map(|event| match event {
+        KamilataEvent::IncompatiblePeer { peer_id, reason } => Some((peer_id, reason)),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn handshake() -> Result<(), Box<dyn std::error::Error>> {
+    let client1 = Client::init().await;
+    let mut client2 = Client::init().await;
+    let client3_key = Keypair::generate_ed25519();
+    let client3_peer_id = client3_key.public().to_peer_id();
+    let behaviour = KamilataBehaviour::<1000, MovieIndex<1000>>::new(client3_peer_id);
+    let mut client3 = Swarm::new(memory_transport(client3_key)?, behaviour, client3_peer_id, SwarmConfig::with_tokio_executor());
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3_peer_id, "client 3");
+    logger.activate();
+
+    client2.swarm_mut().dial(DialOpts::peer_id(client1.peer_id()).addresses(vec![client1.addr().to_owned()]).build()).unwrap();
+    client3.dial(DialOpts::peer_id(client1.peer_id()).addresses(vec![client1.addr().to_owned()]).build()).unwrap();
+    let c1 = client1.run();
+    let c2 = client2.run();
+
+    info!("Waiting for client 3 to be refused...");
+    let mut connected = false;
+    let reason = loop {
+        match timeout(Duration::from_secs(5), client3.select_next_some()).await? {
+            SwarmEvent::ConnectionEstablished { .. } => connected = true,
+            SwarmEvent::Behaviour(KamilataEvent::IncompatiblePeer { peer_id, reason }) if peer_id == c1.peer_id() => break reason,
+            _ => (),
+        }
+    };
+    assert!(connected);
+    assert_eq!(reason, Incompatibility::FilterSize { ours: 1000, theirs: 125000 });
+    loop {
+        if let SwarmEvent::ConnectionClosed { peer_id, .. } = timeout(Duration::from_secs(5), client3.select_next_some()).await? {
+            assert_eq!(peer_id, c1.peer_id());
+            break;
+        }
+    }
+
+    assert_eq!(incompatibility(c1.take_events().await), Some((client3_peer_id, Incompatibility::FilterSize { ours: 125000, theirs: 1000 })));
+    assert_eq!(incompatibility(c2.take_events().await), None);
+
+    // Compatible peers stay connected
+    c2.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-///! A test to make sure directives that from KamilataConfig (fields in_routing_peers and out_routing_peers) are respected.
+//! A test to make sure directives that from KamilataConfig (fields in_routing_peers and out_routing_peers) are respected.
 
 mod common;
 use common::*;
@@ -9,9 +9,13 @@ async fn limits() -> Result<(), Box<dyn std::error::Error>> {
         protocol_names: vec![String::from("/kamilata/0.1.0")],
         max_seeders: 5,
         max_leechers: 5,
+        max_filter_load: 0.5,
         get_filters_interval: MinTargetMax::new(60_000_000, 60_000_000, 60_000_000),
         filter_count: 0,
         approve_leecher: None,
+        leecher_policy: None,
+        auto_leech: None,
+        seeder_rotation: None,
     };
 
     info!("Initializing clients...");
@@ -1,4 +1,5 @@
 mod common;
+#[allow(unused_imports)]
 use common::*;
 
 /*
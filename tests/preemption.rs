@@ -0,0 +1,80 @@
+//! A test making sure leechers with a higher priority preempt the others when all leecher spots are taken.
+//! Client 1 has a single leecher spot and gives client 3 a higher priority.
+//! Client 2 leeches first, then loses its spot to client 3 and can't take it back.
+//! Clients 4 and 5, which have the same priority as client 3 and a lower one, are refused.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn preemption() -> Result<(), Box<dyn std::error::Error>> {
+    let client2 = Client::init().await;
+    let client3 = Client::init().await;
+    let client4 = Client::init().await;
+    let client5 = Client::init().await;
+    let high_priority_peers = [client3.peer_id(), client4.peer_id()];
+    let mut client1 = Client::init_with_config(KamilataConfig {
+        max_leechers: 1,
+        leecher_policy: Some(Box::new(move |peer_id| if high_priority_peers.contains(&peer_id) { 1 } else { 0 })),
+        ..Default::default()
+    }).await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.with_alias(client4.peer_id(), "client 4");
+    logger.with_alias(client5.peer_id(), "client 5");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client1.swarm_mut().dial(DialOpts::peer_id(client3.peer_id()).addresses(vec![client3.addr().to_owned()]).build()).unwrap();
+    client1.swarm_mut().dial(DialOpts::peer_id(client4.peer_id()).addresses(vec![client4.addr().to_owned()]).build()).unwrap();
+    client1.swarm_mut().dial(DialOpts::peer_id(client5.peer_id()).addresses(vec![client5.addr().to_owned()]).build()).unwrap();
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+    let c4 = client4.run();
+    let c5 = client5.run();
+    sleep(Duration::from_secs(1)).await;
+
+    info!("Client 2 leeches from client 1...");
+    c2.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+    assert_eq!(c2.get_routing_stats().await, (1, 0));
+
+    info!("Client 3 takes the spot of client 2...");
+    c3.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+    assert_eq!(c3.get_routing_stats().await, (1, 0));
+
+    // Client 2 is told when to come back, and only stops leeching until then
+    let rejection = c2.take_events().await.into_iter().find_map(|event| match event {
+        KamilataEvent::LeechRejected { reason, retry_in, .. } => Some((reason, retry_in)),
+        _ => None,
+    });
+    assert_eq!(rejection, Some((RejectionReason::Preempted, Some(DEFAULT_PREEMPTED_LEECHER_RETRY_DELAY))));
+    assert!(c2.is_connected(&c1).await);
+
+    info!("Client 2 tries to leech again...");
+    c2.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+    assert_eq!(c3.get_routing_stats().await, (1, 0));
+
+    info!("Clients 4 and 5 try to take the spot of client 3...");
+    c4.leech_from(&c1).await;
+    c5.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+    assert_eq!(c3.get_routing_stats().await, (1, 0));
+    assert_eq!(c4.get_routing_stats().await, (0, 0));
+    assert_eq!(c5.get_routing_stats().await, (0, 0));
+
+    Ok(())
+}
@@ -2,6 +2,7 @@
 //! In both tests, two documents are added to the network:
 //!   - A perfectly matching document at a distance of 2
 //!   - A partially matching document at a distance of 1
+//!
 //! Depending on the priority, the order of the results should be different.
 
 mod common;
@@ -0,0 +1,43 @@
+//! Tests making sure seeders push their filters early when their store changes.
+//! Client 1 leeches from client 2, with a periodic refresh too slow for the test to rely on.
+
+mod common;
+use common::*;
+
+fn node_config() -> KamilataConfig {
+    KamilataConfig {
+        get_filters_interval: MinTargetMax::new(500, 60_000, 60_000),
+        ..KamilataConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn push_refresh() {
+    let mut client1 = Client::init_with_config(node_config()).await;
+    let client2 = Client::init_with_config(node_config()).await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    sleep(Duration::from_secs(1)).await;
+
+    // Without notification, the document is only advertised at the next periodic update
+    c2.insert_document(Movie::titled(0, "Unnoticed"), false).await;
+    sleep(Duration::from_secs(1)).await;
+    assert!(c1.search(["unnoticed"].as_slice()).await.hits.is_empty());
+
+    // With notification, both documents are advertised right away
+    c2.insert_document(Movie::titled(1, "Pushed"), true).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.search(["pushed"].as_slice()).await.hits.len(), 1);
+    assert_eq!(c1.search(["unnoticed"].as_slice()).await.hits.len(), 1);
+}
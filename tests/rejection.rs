@@ -0,0 +1,66 @@
+//! A test making sure peers we refuse to seed to are told why.
+//! Client 1 has a single leecher spot, doesn't approve client 3 and blocks client 5.
+//! Client 2 takes the spot, then clients 3, 4 and 5 are rejected for different reasons.
+
+mod common;
+use common::*;
+
+fn find_rejection(events: Vec<KamilataEvent>) -> Option<(libp2p::PeerId, RejectionReason, Option<Duration>)> {
+    events.into_iter().find_map(|event| match event {
+        KamilataEvent::LeechRejected { peer_id, reason, retry_in } => Some((peer_id, reason, retry_in)),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn rejection() -> Result<(), Box<dyn std::error::Error>> {
+    let client2 = Client::init().await;
+    let client3 = Client::init().await;
+    let client4 = Client::init().await;
+    let client5 = Client::init().await;
+    let client3_peer_id = client3.peer_id();
+    let mut client1 = Client::init_with_config(KamilataConfig {
+        max_leechers: 1,
+        approve_leecher: Some(Box::new(move |peer_id| Box::pin(async move { peer_id != client3_peer_id }))),
+        ..Default::default()
+    }).await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.with_alias(client4.peer_id(), "client 4");
+    logger.with_alias(client5.peer_id(), "client 5");
+    logger.activate();
+
+    for client in [&client2, &client3, &client4, &client5] {
+        client1.swarm_mut().dial(DialOpts::peer_id(client.peer_id()).addresses(vec![client.addr().to_owned()]).build()).unwrap();
+    }
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+    let c4 = client4.run();
+    let c5 = client5.run();
+    sleep(Duration::from_secs(1)).await;
+    c1.block_peer(&c5).await;
+
+    c2.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+    assert_eq!(find_rejection(c2.take_events().await), None);
+
+    c3.leech_from(&c1).await;
+    c4.leech_from(&c1).await;
+    c5.leech_from(&c1).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 1));
+    assert_eq!(find_rejection(c3.take_events().await), Some((c1.peer_id(), RejectionReason::NotApproved, None)));
+    assert_eq!(find_rejection(c4.take_events().await), Some((c1.peer_id(), RejectionReason::TooManyLeechers, Some(Duration::from_secs(60)))));
+    assert_eq!(find_rejection(c5.take_events().await), Some((c1.peer_id(), RejectionReason::Blocked, None)));
+    for c in [&c3, &c4, &c5] {
+        assert_eq!(c.get_routing_stats().await, (0, 0));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! A test making sure useless seeders are replaced by better candidates.
+//! Client 1 has a single seeder slot and starts leeching from client 2, which has no document.
+//! Once client 3, which has a document, connects, client 2 is evicted in its favour, and client 1 then sticks with client 3.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn rotation() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client1 = Client::init_with_config(KamilataConfig {
+        max_seeders: 1,
+        auto_leech: Some(Box::new(SeederSelection::MostUniqueCoverage)),
+        seeder_rotation: Some(SeederRotation {
+            interval: Duration::from_millis(500),
+            min_seeder_age: Duration::ZERO,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }).await;
+    let client2 = Client::init().await;
+    let client3 = Client::init().await;
+    let addr3 = client3.addr().clone();
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client3.store().insert_document(Movie::titled(0, "Useful document")).await;
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (1, 0));
+    assert_eq!(c2.get_routing_stats().await, (0, 1));
+
+    info!("Connecting client 3...");
+    c1.dial(addr3).await;
+    sleep(Duration::from_secs(2)).await;
+    assert_eq!(c1.get_routing_stats().await, (1, 0));
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+    assert_eq!(c3.get_routing_stats().await, (0, 1));
+
+    // Client 2 brings nothing, so client 3 is kept
+    sleep(Duration::from_secs(2)).await;
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+    assert_eq!(c3.get_routing_stats().await, (0, 1));
+
+    Ok(())
+}
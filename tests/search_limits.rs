@@ -0,0 +1,83 @@
+//! Tests making sure searches stop when reaching the limits set in their config, or when cancelled.
+//! Client 1 leeches from clients 2 and 3, which both have a matching document.
+
+mod common;
+use common::*;
+
+async fn init_network() -> (ClientController, ClientController, ClientController) {
+    let mut client1 = Client::init().await;
+    let client2 = Client::init().await;
+    let client3 = Client::init().await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.activate();
+
+    client1.swarm_mut().dial(DialOpts::peer_id(client2.peer_id()).addresses(vec![client2.addr().to_owned()]).build()).unwrap();
+    client1.swarm_mut().dial(DialOpts::peer_id(client3.peer_id()).addresses(vec![client3.addr().to_owned()]).build()).unwrap();
+    for (id, client) in [&client2, &client3].into_iter().enumerate() {
+        client.store().insert_document(Movie::perfect_match(id)).await;
+    }
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    c1.leech_from(&c3).await;
+
+    info!("Waiting for filters to propagate...");
+    sleep(Duration::from_secs(1)).await;
+
+    (c1, c2, c3)
+}
+
+fn finish_reason(events: &[SearchEvent]) -> Option<SearchFinishReason> {
+    match events.last() {
+        Some(SearchEvent::Finished { reason }) => Some(reason.clone()),
+        _ => None,
+    }
+}
+
+fn queried_count(events: &[SearchEvent]) -> usize {
+    events.iter().filter(|e| matches!(e, SearchEvent::PeerQueried(..))).count()
+}
+
+#[tokio::test]
+async fn search_limits() {
+    let (c1, _c2, _c3) = init_network().await;
+
+    let (events, results) = c1.search_events(["perfectly"].as_slice(), SearchConfig::default()).await;
+    assert_eq!(finish_reason(&events), Some(SearchFinishReason::ProvidersExhausted));
+    assert_eq!(results.hits.len(), 2);
+
+    let (events, results) = c1.search_events(["perfectly"].as_slice(), SearchConfig::default().with_max_results(1)).await;
+    assert_eq!(finish_reason(&events), Some(SearchFinishReason::ResultLimitReached));
+    assert_eq!(results.hits.len(), 1);
+
+    let (events, results) = c1.search_events(["perfectly"].as_slice(), SearchConfig::default().with_max_peers(1)).await;
+    assert_eq!(finish_reason(&events), Some(SearchFinishReason::PeerLimitReached));
+    assert_eq!(queried_count(&events), 1);
+    assert_eq!(results.hits.len(), 1);
+
+    let (events, results) = c1.search_events(["perfectly"].as_slice(), SearchConfig::default().with_deadline(Duration::ZERO)).await;
+    assert_eq!(finish_reason(&events), Some(SearchFinishReason::DeadlineReached));
+    assert_eq!(results.hits.len(), 0);
+}
+
+#[tokio::test]
+async fn search_cancellation() {
+    let (c1, _c2, _c3) = init_network().await;
+
+    let events = c1.search_and_cancel(["perfectly"].as_slice()).await;
+    assert_eq!(finish_reason(&events), Some(SearchFinishReason::Cancelled));
+    assert!(queried_count(&events) >= 1);
+
+    // The peers are still willing to answer later searches
+    let (events, results) = c1.search_events(["perfectly"].as_slice(), SearchConfig::default()).await;
+    assert_eq!(finish_reason(&events), Some(SearchFinishReason::ProvidersExhausted));
+    assert_eq!(results.hits.len(), 2);
+}
@@ -0,0 +1,48 @@
+//! A test making sure the behaviour chooses seeders by itself when auto-leeching is enabled.
+//! Clients 2 and 3 connect to client 1, which has a single seeder slot.
+//! Client 1 leeches from one of them without being told to, and replaces it with the other once it disconnects.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn topology() -> Result<(), Box<dyn std::error::Error>> {
+    let client1 = Client::init_with_config(KamilataConfig {
+        max_seeders: 1,
+        auto_leech: Some(Box::new(SeederSelection::MostDocuments)),
+        ..Default::default()
+    }).await;
+    let mut client2 = Client::init().await;
+    let mut client3 = Client::init().await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.with_alias(client3.peer_id(), "client 3");
+    logger.activate();
+
+    client2.swarm_mut().dial(DialOpts::peer_id(client1.peer_id()).addresses(vec![client1.addr().to_owned()]).build()).unwrap();
+    client3.swarm_mut().dial(DialOpts::peer_id(client1.peer_id()).addresses(vec![client1.addr().to_owned()]).build()).unwrap();
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+    let c3 = client3.run();
+
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (1, 0));
+    let (seeder, other) = match c2.get_routing_stats().await {
+        (0, 1) => (&c2, &c3),
+        _ => (&c3, &c2),
+    };
+    assert_eq!(seeder.get_routing_stats().await, (0, 1));
+    assert_eq!(other.get_routing_stats().await, (0, 0));
+
+    info!("Disconnecting the seeder...");
+    seeder.disconnect(&c1, None).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (1, 0));
+    assert_eq!(seeder.get_routing_stats().await, (0, 0));
+    assert_eq!(other.get_routing_stats().await, (0, 1));
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+//! A test making sure seeders sending invalid filters are disconnected.
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn overloaded_filters() {
+    // Any filter with a single bit set is considered overloaded by client 1
+    let client1 = Client::init_with_config(KamilataConfig { max_filter_load: 0.0, ..KamilataConfig::default() }).await;
+    let client2 = Client::init().await;
+    let addr2 = client2.addr().clone();
+    client2.store().insert_document(Movie::titled(0, "Poison")).await;
+
+    let mut logger = ClientLogger::new();
+    logger.with_alias(client1.peer_id(), "client 1");
+    logger.with_alias(client2.peer_id(), "client 2");
+    logger.activate();
+
+    let c1 = client1.run();
+    let c2 = client2.run();
+
+    c1.dial(addr2).await;
+    sleep(Duration::from_secs(1)).await;
+    c1.leech_from(&c2).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 0));
+    assert_eq!(c2.get_routing_stats().await, (0, 0));
+
+    // Client 1 refuses to leech from client 2 again
+    c1.leech_from(&c2).await;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(c1.get_routing_stats().await, (0, 0));
+}